@@ -0,0 +1,50 @@
+{prelude}
+/// Keeps the optimizer from removing the benchmarked code.
+fn black_box<T>(dummy: T) -> T {{
+    unsafe {{
+        let ret = ::std::ptr::read_volatile(&dummy);
+        ::std::mem::forget(dummy);
+        ret
+    }}
+}}
+
+/// Runs `f` `iterations` times, returning the time per iteration in nanoseconds.
+fn run_batch<T, F: FnMut() -> T>(f: &mut F, iterations: u64) -> f64 {{
+    let start = ::std::time::Instant::now();
+
+    for _ in 0..iterations {{
+        black_box(f());
+    }}
+
+    let elapsed = start.elapsed();
+    (elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64) / iterations as f64
+}}
+
+/// Samples the time per iteration of `f` in batches of about a millisecond.
+fn bench<T, F: FnMut() -> T>(mut f: F) -> Vec<f64> {{
+    let mut iterations = 1;
+
+    while iterations < 1 << 30 && run_batch(&mut f, iterations) * (iterations as f64) < 1e6 {{
+        iterations *= 2;
+    }}
+
+    for _ in 0..{warmup} {{
+        run_batch(&mut f, iterations);
+    }}
+
+    (0..{samples}).map(|_| run_batch(&mut f, iterations)).collect()
+}}
+
+fn report(candidate: usize, mut samples: Vec<f64>) {{
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let median = samples[samples.len() / 2];
+    let variance = samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+    println!("{marker}{{}} {{}} {{}} {{}}", candidate, mean, median, variance.sqrt());
+}}
+
+fn main() {{
+{candidates}}}
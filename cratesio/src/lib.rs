@@ -3,14 +3,18 @@ extern crate url;
 #[macro_use]
 extern crate serde_derive;
 
+use reqwest::Client;
 use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
 
-pub fn crate_info(name: &str) -> Result<Info, reqwest::Error> {
+pub fn crate_info(client: &Client, name: &str) -> Result<Info, reqwest::Error> {
     let url = format!(
         "https://crates.io/api/v1/crates/{}",
         utf8_percent_encode(name, PATH_SEGMENT_ENCODE_SET).collect::<String>()
     );
-    let info = reqwest::get(&url)?
+    let info = client
+        .get(&url)
+        .send()?
+        .error_for_status()?
         .json()?;
 
     Ok(info)
@@ -0,0 +1,150 @@
+use {Channel, Crates, ExecuteRequest, ExecuteResponse, Version, crates, execute, version};
+use reqwest::{Client, Error};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Caches the responses of `execute`, `version` and `crates` for a while.
+/// Identical requests made while one is in flight wait for its response
+/// instead of being sent again.
+pub struct Cache {
+    ttl: Duration,
+    executions: Memo<ExecuteRequest<'static>, ExecuteResponse>,
+    versions: Memo<Channel, Version>,
+    crates: Memo<(), Crates>,
+    /// When fetching the crates last failed
+    crates_failed_at: Mutex<Option<Instant>>,
+}
+
+impl Cache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            executions: Memo::new(ttl),
+            versions: Memo::new(ttl),
+            crates: Memo::new(ttl),
+            crates_failed_at: Mutex::new(None),
+        }
+    }
+
+    /// Runs the request, keeping the response for identical requests if it is `cacheable`,
+    /// e.g. unless the run was cut short by the playground being busy.
+    pub fn execute<F>(&self, client: &Client, req: &ExecuteRequest, cacheable: F) -> Result<ExecuteResponse, Error>
+    where
+        F: FnOnce(&ExecuteResponse) -> bool,
+    {
+        self.executions.get_or_fetch(req.clone().into_owned(), || execute(client, req), cacheable)
+    }
+
+    pub fn version(&self, client: &Client, channel: Channel) -> Result<Version, Error> {
+        self.versions.get_or_fetch(channel, || version(client, channel), |_| true)
+    }
+
+    pub fn crates(&self, client: &Client) -> Result<Crates, Error> {
+        let result = self.crates.get_or_fetch((), || crates(client), |_| true);
+        *self.crates_failed_at.lock().unwrap() = result.as_ref().err().map(|_| Instant::now());
+
+        result
+    }
+
+    /// Whether fetching the crates failed within the TTL,
+    /// so optional lookups can skip them instead of waiting for the service again.
+    pub fn crates_failing(&self) -> bool {
+        self.crates_failed_at.lock().unwrap()
+            .map_or(false, |failed_at| failed_at.elapsed() < self.ttl)
+    }
+}
+
+enum Entry<V> {
+    /// A request for the value is in flight
+    Pending,
+    Ready(V, Instant),
+}
+
+struct Memo<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, Entry<V>>>,
+    fetched: Condvar,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Memo<K, V> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            fetched: Condvar::new(),
+        }
+    }
+
+    /// Returns the cached value for `key`, fetching it if there is none.
+    /// Failed fetches and values that aren't `cacheable` are not cached,
+    /// so waiting requests try again themselves.
+    fn get_or_fetch<E, F, C>(&self, key: K, fetch: F, cacheable: C) -> Result<V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+        C: FnOnce(&V) -> bool,
+    {
+        let mut entries = self.entries.lock().unwrap();
+
+        loop {
+            let pending = match entries.get(&key) {
+                Some(&Entry::Ready(ref value, fetched_at)) if fetched_at.elapsed() < self.ttl => {
+                    return Ok(value.clone());
+                },
+                Some(&Entry::Pending) => true,
+                _ => false,
+            };
+
+            if !pending {
+                break;
+            }
+
+            entries = self.fetched.wait(entries).unwrap();
+        }
+
+        entries.insert(key.clone(), Entry::Pending);
+        drop(entries);
+
+        let mut fetching = Fetching { memo: self, key: Some(key) };
+        let value = fetch()?;
+
+        // Otherwise dropping `fetching` removes the pending entry
+        if cacheable(&value) {
+            fetching.finish(value.clone());
+        }
+
+        Ok(value)
+    }
+}
+
+/// Resolves a pending entry, removing it if the fetch failed or panicked.
+struct Fetching<'a, K: 'a + Hash + Eq, V: 'a> {
+    memo: &'a Memo<K, V>,
+    key: Option<K>,
+}
+
+impl<'a, K: Hash + Eq, V> Fetching<'a, K, V> {
+    fn finish(&mut self, value: V) {
+        if let Some(key) = self.key.take() {
+            let ttl = self.memo.ttl;
+            let mut entries = self.memo.entries.lock().unwrap();
+
+            entries.retain(|_, entry| match *entry {
+                Entry::Pending => true,
+                Entry::Ready(_, fetched_at) => fetched_at.elapsed() < ttl,
+            });
+            entries.insert(key, Entry::Ready(value, Instant::now()));
+            self.memo.fetched.notify_all();
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq, V> Drop for Fetching<'a, K, V> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.memo.entries.lock().unwrap().remove(&key);
+            self.memo.fetched.notify_all();
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use {CrateType, ExecuteResponse};
+use std::borrow::Cow;
+use reqwest::{Client, Error};
+
+pub fn clippy(client: &Client, req: &Request) -> Result<ExecuteResponse, Error> {
+    let resp = client
+        .post("https://play.rust-lang.org/clippy")
+        .json(req)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(resp)
+}
+
+#[derive(Serialize,Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Request<'a> {
+    code: Cow<'a, str>,
+    crate_type: CrateType,
+}
+
+impl<'a> Request<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(code: S) -> Self {
+        Self {
+            code: code.into(),
+            crate_type: CrateType::Bin,
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
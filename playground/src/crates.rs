@@ -0,0 +1,53 @@
+use reqwest::{Client, Error};
+
+/// Fetches the crates that code on the playground can use.
+pub fn crates(client: &Client) -> Result<Crates, Error> {
+    let resp = client
+        .get("https://play.rust-lang.org/meta/crates")
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(resp)
+}
+
+#[derive(Deserialize,Clone)]
+pub struct Crates {
+    crates: Vec<Crate>,
+}
+
+impl Crates {
+    pub fn crates(&self) -> &[Crate] {
+        &self.crates
+    }
+
+    /// Finds a crate by its name or by the name code refers to it with,
+    /// e.g. `serde-json` or `serde_json`.
+    pub fn find(&self, name: &str) -> Option<&Crate> {
+        let name = name.replace('-', "_");
+
+        self.crates.iter().find(|krate| krate.id == name || krate.name.replace('-', "_") == name)
+    }
+}
+
+#[derive(Deserialize,Clone)]
+pub struct Crate {
+    name: String,
+    version: String,
+    id: String,
+}
+
+impl Crate {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The name code refers to the crate with
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -1,8 +1,22 @@
-use {Channel, CrateType, Mode};
+use {Channel, CrateType, Edition, Mode};
+use stdin;
 use std::borrow::Cow;
 use reqwest::{Client, Error};
 
 pub fn execute(client: &Client, req: &Request) -> Result<Response, Error> {
+    let wrapped;
+    let req = match req.stdin {
+        Some(ref input) => {
+            wrapped = Request {
+                code: stdin::wrap(&req.code, input).into(),
+                stdin: None,
+                ..req.clone()
+            };
+            &wrapped
+        },
+        None => req,
+    };
+
     let resp = client
         .post("https://play.rust-lang.org/execute")
         .json(req)
@@ -13,14 +27,19 @@ pub fn execute(client: &Client, req: &Request) -> Result<Response, Error> {
     Ok(resp)
 }
 
-#[derive(Serialize,Debug)]
+#[derive(Serialize,Debug,Clone,PartialEq,Eq,Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Request<'a> {
     code: Cow<'a, str>,
     channel: Channel,
     crate_type: CrateType,
     mode: Mode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edition: Option<Edition>,
     tests: bool,
+    /// Input of the program, which the playground itself doesn't support
+    #[serde(skip)]
+    stdin: Option<Cow<'a, str>>,
 }
 
 impl<'a> Request<'a> {
@@ -30,7 +49,22 @@ impl<'a> Request<'a> {
             channel: Channel::Stable,
             crate_type: CrateType::Bin,
             mode: Mode::Debug,
+            edition: None,
             tests: false,
+            stdin: None,
+        }
+    }
+
+    /// Converts the request into one that owns its code.
+    pub fn into_owned(self) -> Request<'static> {
+        Request {
+            code: Cow::Owned(self.code.into_owned()),
+            channel: self.channel,
+            crate_type: self.crate_type,
+            mode: self.mode,
+            edition: self.edition,
+            tests: self.tests,
+            stdin: self.stdin.map(|stdin| Cow::Owned(stdin.into_owned())),
         }
     }
 
@@ -53,9 +87,25 @@ impl<'a> Request<'a> {
     pub fn set_mode(&mut self, mode: Mode) {
         self.mode = mode;
     }
+
+    pub fn edition(&self) -> Option<Edition> {
+        self.edition
+    }
+
+    pub fn set_edition(&mut self, edition: Edition) {
+        self.edition = Some(edition);
+    }
+
+    pub fn stdin(&self) -> Option<&str> {
+        self.stdin.as_ref().map(|stdin| &**stdin)
+    }
+
+    pub fn set_stdin<S: Into<Cow<'a, str>>>(&mut self, stdin: S) {
+        self.stdin = Some(stdin.into());
+    }
 }
 
-#[derive(Deserialize,Debug)]
+#[derive(Deserialize,Debug,Clone)]
 pub struct Response {
     pub stderr: String,
     pub stdout: String,
@@ -0,0 +1,38 @@
+use std::borrow::Cow;
+use reqwest::{Client, Error};
+
+pub fn format(client: &Client, req: &Request) -> Result<Response, Error> {
+    let resp = client
+        .post("https://play.rust-lang.org/format")
+        .json(req)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(resp)
+}
+
+#[derive(Serialize,Debug)]
+pub struct Request<'a> {
+    code: Cow<'a, str>,
+}
+
+impl<'a> Request<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(code: S) -> Self {
+        Self {
+            code: code.into(),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+#[derive(Deserialize,Debug)]
+pub struct Response {
+    pub code: String,
+    pub stderr: String,
+    pub stdout: String,
+    pub success: bool,
+}
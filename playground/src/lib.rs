@@ -3,7 +3,10 @@ extern crate reqwest;
 #[macro_use]
 extern crate serde_derive;
 
-use std::str;
+use std::str::{self, FromStr};
+use failure::{Error, err_msg};
+
+mod stdin;
 
 pub mod execute;
 pub use execute::{
@@ -15,10 +18,43 @@ pub use execute::{
 mod version;
 pub use version::{version, Version};
 
+mod cache;
+pub use cache::Cache;
+
+mod crates;
+pub use crates::{crates, Crates, Crate};
+
 pub mod paste;
-pub use paste::paste;
+pub use paste::{paste, gist, Gist};
+
+pub mod clippy;
+pub use clippy::{
+    clippy,
+    Request as ClippyRequest,
+};
+
+pub mod format;
+pub use format::{
+    format,
+    Request as FormatRequest,
+    Response as FormatResponse,
+};
+
+pub mod miri;
+pub use miri::{
+    miri,
+    Request as MiriRequest,
+};
+
+pub mod local;
+
+pub mod macro_expansion;
+pub use macro_expansion::{
+    expand_macros,
+    Request as MacroExpansionRequest,
+};
 
-#[derive(Serialize,Debug,Copy,Clone)]
+#[derive(Serialize,Debug,Copy,Clone,PartialEq,Eq,Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Mode {
     Debug,
@@ -34,13 +70,25 @@ impl Mode {
     }
 }
 
-#[derive(Serialize,Debug)]
+impl FromStr for Mode {
+    type Err = Error;
+
+    fn from_str(mode: &str) -> Result<Self, Error> {
+        match mode {
+            "debug" => Ok(Mode::Debug),
+            "release" => Ok(Mode::Release),
+            _ => Err(err_msg(format!("Unknown mode '{}'", mode))),
+        }
+    }
+}
+
+#[derive(Serialize,Debug,Copy,Clone,PartialEq,Eq,Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum CrateType {
     Bin,
 }
 
-#[derive(Serialize,Debug,Copy,Clone)]
+#[derive(Serialize,Debug,Copy,Clone,PartialEq,Eq,Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Channel {
     Stable,
@@ -58,14 +106,44 @@ impl Channel {
     }
 }
 
-#[derive(Deserialize)]
-pub struct Crates {
-    crates: Vec<Crate>,
+impl FromStr for Channel {
+    type Err = Error;
+
+    fn from_str(channel: &str) -> Result<Self, Error> {
+        match channel {
+            "stable" => Ok(Channel::Stable),
+            "beta" => Ok(Channel::Beta),
+            "nightly" => Ok(Channel::Nightly),
+            _ => Err(err_msg(format!("Unknown channel '{}'", channel))),
+        }
+    }
+}
+
+#[derive(Serialize,Debug,Copy,Clone,PartialEq,Eq,Hash)]
+pub enum Edition {
+    #[serde(rename = "2015")]
+    E2015,
+    #[serde(rename = "2018")]
+    E2018,
 }
 
-#[derive(Deserialize)]
-pub struct Crate {
-    name: String,
-    version: String,
-    id: String,
+impl Edition {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+        }
+    }
+}
+
+impl FromStr for Edition {
+    type Err = Error;
+
+    fn from_str(edition: &str) -> Result<Self, Error> {
+        match edition {
+            "2015" => Ok(Edition::E2015),
+            "2018" => Ok(Edition::E2018),
+            _ => Err(err_msg(format!("Unknown edition '{}'", edition))),
+        }
+    }
 }
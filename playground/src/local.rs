@@ -0,0 +1,386 @@
+//! Local execution backend, compiling code with the rustup toolchains
+//! installed on the host instead of sending it to the playground.
+//!
+//! The compiler and the program run in an nsjail sandbox without network access.
+//! It only sees its build directory and read-only mounts of the toolchain and some system paths,
+//! and its memory, CPU time, file sizes and open files are limited.
+
+use {ExecuteRequest, ExecuteResponse, Mode};
+use failure::{Error, ResultExt, err_msg};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Child, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static NEXT_BUILD_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+const COMPILE_TIMEOUT_SECS: u64 = 30;
+const TIMEOUT_SECS: u64 = 10;
+/// Output of the compiler or the program beyond this is discarded
+const MAX_OUTPUT_BYTES: u64 = 64 * 1024;
+const MAX_FILE_SIZE_MB: u64 = 64;
+const MAX_OPEN_FILES: u64 = 64;
+const DEFAULT_MEMORY_MB: u64 = 1024;
+/// Needed by the linker and by programs
+const DEFAULT_READ_ONLY: &[&str] = &["/bin", "/lib", "/lib64", "/usr", "/dev/urandom"];
+/// Where the build directory is mounted in the sandbox
+const SANDBOX_DIR: &str = "/playground";
+
+/// The nsjail sandbox that code is compiled and run in.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    nsjail: PathBuf,
+    read_only: Vec<PathBuf>,
+    memory_mb: u64,
+}
+
+impl Sandbox {
+    pub fn new(nsjail: impl Into<PathBuf>) -> Self {
+        Self {
+            nsjail: nsjail.into(),
+            read_only: DEFAULT_READ_ONLY.iter().map(PathBuf::from).collect(),
+            memory_mb: DEFAULT_MEMORY_MB,
+        }
+    }
+
+    /// Sets the paths of the host that are visible read-only, besides the toolchain.
+    /// Paths that don't exist are skipped.
+    pub fn set_read_only(&mut self, paths: Vec<PathBuf>) {
+        self.read_only = paths;
+    }
+
+    /// Sets the address space limit of the compiler and the program.
+    pub fn set_memory_limit(&mut self, megabytes: u64) {
+        self.memory_mb = megabytes;
+    }
+
+    /// Prepares running `program` in the sandbox, with `dir` as its working directory.
+    /// nsjail logs to `log` if given, which has to be outside of `dir`, and only warnings otherwise.
+    fn command(&self, dir: &Path, sysroot: &Path, timeout: Duration, program: &Path, log: Option<&Path>) -> Command {
+        let mut command = Command::new(&self.nsjail);
+
+        command.args(&["--mode", "o"]);
+
+        match log {
+            Some(log) => command.arg("--log").arg(log),
+            None => command.arg("--quiet"),
+        };
+
+        command
+            .arg("--time_limit").arg((timeout.as_secs() + 1).to_string())
+            .arg("--rlimit_as").arg(self.memory_mb.to_string())
+            .arg("--rlimit_cpu").arg(timeout.as_secs().to_string())
+            .arg("--rlimit_fsize").arg(MAX_FILE_SIZE_MB.to_string())
+            .arg("--rlimit_nofile").arg(MAX_OPEN_FILES.to_string())
+            .arg("--bindmount").arg(format!("{}:{}", dir.display(), SANDBOX_DIR))
+            .arg("--cwd").arg(SANDBOX_DIR)
+            .arg("--env").arg("PATH=/usr/local/bin:/usr/bin:/bin")
+            .arg("--env").arg(format!("TMPDIR={}", SANDBOX_DIR));
+
+        let read_only = self.read_only.iter()
+            .map(PathBuf::as_path)
+            .chain(iter::once(sysroot))
+            .filter(|path| path.exists());
+
+        for path in read_only {
+            command.arg("--bindmount_ro").arg(path);
+        }
+
+        command.arg("--").arg(program);
+        command
+    }
+}
+
+/// Statistics of a program that compiled and ran.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub runtime: Duration,
+    /// Peak resident memory in bytes, unless the program exited before it could be sampled
+    pub peak_memory: Option<u64>,
+    pub binary_size: u64,
+}
+
+/// Compiles and runs the request in the sandbox using the given rustup toolchain,
+/// e.g. `1.31.0` or `nightly-2018-06-01`.
+pub fn execute(req: &ExecuteRequest, toolchain: &str, sandbox: &Sandbox) -> Result<ExecuteResponse, Error> {
+    execute_with_stats(req, toolchain, sandbox).map(|(resp, _)| resp)
+}
+
+/// Like `execute`, also returning the statistics of the run if the code compiled.
+pub fn execute_with_stats(req: &ExecuteRequest, toolchain: &str, sandbox: &Sandbox) -> Result<(ExecuteResponse, Option<Stats>), Error> {
+    if !is_valid_toolchain(toolchain) {
+        return Err(err_msg(format!("Invalid toolchain name '{}'", toolchain)));
+    }
+
+    let build_id = NEXT_BUILD_ID.fetch_add(1, Ordering::SeqCst);
+    let dir = env::temp_dir().join(format!("playbot-{}-{}", process::id(), build_id));
+    fs::create_dir_all(&dir)
+        .with_context(|_| format!("Failed to create build dir {}", dir.display()))?;
+
+    // Next to the build dir, where the program can't tamper with it
+    let log = dir.with_extension("log");
+    let result = build_and_run(&dir, &log, req, toolchain, sandbox);
+
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        eprintln!("Failed to remove build dir {}: {:?}", dir.display(), e);
+    }
+
+    // Only exists if the program ran
+    let _ = fs::remove_file(&log);
+
+    result
+}
+
+/// Lists the installed rustup toolchains.
+pub fn toolchains() -> Result<Vec<String>, Error> {
+    let output = Command::new("rustup")
+        .args(&["toolchain", "list"])
+        .output()
+        .context("Failed to run rustup")?;
+
+    let toolchains = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(String::from)
+        .collect();
+
+    Ok(toolchains)
+}
+
+pub fn is_valid_toolchain(toolchain: &str) -> bool {
+    !toolchain.is_empty()
+    && !toolchain.starts_with('-')
+    && toolchain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+}
+
+/// Finds the sysroot of the toolchain, which contains the compiler and the standard library.
+fn sysroot(toolchain: &str) -> Result<PathBuf, Error> {
+    let output = Command::new("rustup")
+        .args(&["run", toolchain, "rustc", "--print", "sysroot"])
+        .output()
+        .context("Failed to run rustup")?;
+
+    if !output.status.success() {
+        return Err(err_msg(format!("Toolchain '{}' is not installed", toolchain)));
+    }
+
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+fn build_and_run(dir: &Path, log: &Path, req: &ExecuteRequest, toolchain: &str, sandbox: &Sandbox) -> Result<(ExecuteResponse, Option<Stats>), Error> {
+    let sysroot = sysroot(toolchain)?;
+    let sandbox_dir = Path::new(SANDBOX_DIR);
+
+    fs::write(dir.join("main.rs"), req.code())?;
+
+    let opt_level = match req.mode() {
+        Mode::Debug => "opt-level=0",
+        Mode::Release => "opt-level=3",
+    };
+
+    let compile_timeout = Duration::from_secs(COMPILE_TIMEOUT_SECS);
+    let mut rustc = sandbox.command(dir, &sysroot, compile_timeout, &sysroot.join("bin").join("rustc"), None);
+    rustc.args(&["--crate-name", "playground", "-C", opt_level]);
+
+    if let Some(edition) = req.edition() {
+        rustc.arg(format!("--edition={}", edition.as_str()));
+    }
+
+    let rustc = rustc
+        .arg("-o").arg(sandbox_dir.join("playground"))
+        .arg(sandbox_dir.join("main.rs"))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run rustc in the sandbox")?;
+
+    let build = wait_with_timeout(rustc, compile_timeout)?;
+    let mut stderr = build.stderr;
+
+    if build.timed_out {
+        stderr.push_str(&format!("\nKilled: compilation timed out after {}s\n", COMPILE_TIMEOUT_SECS));
+    }
+
+    if !build.success {
+        return Ok((ExecuteResponse {
+            stderr,
+            stdout: String::new(),
+            success: false,
+        }, None));
+    }
+
+    let binary_size = fs::metadata(dir.join("playground"))?.len();
+
+    let stdin = if req.stdin().is_some() { Stdio::piped() } else { Stdio::null() };
+    let timeout = Duration::from_secs(TIMEOUT_SECS);
+
+    let mut child = sandbox.command(dir, &sysroot, timeout, &sandbox_dir.join("playground"), Some(log))
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run compiled program in the sandbox")?;
+
+    if let (Some(mut writer), Some(input)) = (child.stdin.take(), req.stdin()) {
+        let input = input.to_string();
+
+        // Written in the background, so programs that don't read all of it don't block us
+        thread::spawn(move || {
+            let _ = writer.write_all(input.as_bytes());
+        });
+    }
+
+    let run = wait_with_timeout(child, timeout)?;
+
+    // Like cargo, so compiler output can be told apart from the program's
+    stderr.push_str("     Running `playground`\n");
+    stderr.push_str(&run.stderr);
+
+    if run.timed_out {
+        stderr.push_str(&format!("\nKilled: process timed out after {}s\n", TIMEOUT_SECS));
+    } else if !run.success {
+        let log = fs::read_to_string(log).unwrap_or_default();
+
+        for line in log.lines().filter(|line| !line.starts_with("[I]")) {
+            eprintln!("nsjail: {}", line);
+        }
+
+        stderr.push_str(&describe_failure(run.code, termination_signal(&log)));
+    }
+
+    let stats = Stats {
+        runtime: run.runtime,
+        peak_memory: run.peak_memory,
+        binary_size,
+    };
+
+    Ok((ExecuteResponse {
+        stderr,
+        stdout: run.stdout,
+        success: run.success,
+    }, Some(stats)))
+}
+
+/// Describes how the program failed, in the words of cargo on the playground.
+fn describe_failure(code: Option<i32>, signal: Option<String>) -> String {
+    let status = match (signal, code) {
+        (Some(signal), _) => format!("signal: {}", signal),
+        (None, Some(code)) => format!("exit code: {}", code),
+        (None, None) => return String::new(),
+    };
+
+    format!("\nprocess didn't exit successfully: `playground` ({})\n", status)
+}
+
+/// Finds the signal that killed the program in nsjail's log, e.g. `11, SIGSEGV`.
+/// nsjail exits with 128 plus the signal then, which a program could do as well.
+fn termination_signal(log: &str) -> Option<String> {
+    const TERMINATED: &str = "terminated with signal: ";
+
+    // e.g. `pid=42 ([STANDALONE MODE]) terminated with signal: SIGSEGV (11), (PIDs left: 0)`
+    let line = log.lines().find(|line| line.contains(TERMINATED))?;
+    let rest = &line[line.find(TERMINATED)? + TERMINATED.len()..];
+    let name = rest.split_whitespace().next()?;
+    let number = rest.split('(').nth(1)?.split(')').next()?;
+
+    Some(format!("{}, {}", number, name))
+}
+
+struct Run {
+    stdout: String,
+    stderr: String,
+    success: bool,
+    /// The exit code of the sandbox, unless it was killed
+    code: Option<i32>,
+    timed_out: bool,
+    runtime: Duration,
+    peak_memory: Option<u64>,
+}
+
+fn wait_with_timeout(mut child: Child, timeout: Duration) -> Result<Run, Error> {
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let start = Instant::now();
+    let mut peak_memory = None;
+
+    let (success, code, timed_out) = loop {
+        // The high water mark only grows, so the last sample is the peak
+        peak_memory = sample_peak_memory(child.id()).or(peak_memory);
+
+        if let Some(status) = child.try_wait()? {
+            break (status.success(), status.code(), false);
+        }
+
+        if start.elapsed() > timeout {
+            child.kill()?;
+            child.wait()?;
+            break (false, None, true);
+        }
+
+        thread::sleep(Duration::from_millis(5));
+    };
+
+    let runtime = start.elapsed();
+
+    Ok(Run {
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+        success,
+        code,
+        timed_out,
+        runtime,
+        peak_memory,
+    })
+}
+
+/// Reads the peak resident memory in bytes of the largest process in the tree of `pid`,
+/// which is the program rather than the sandbox around it (Linux only).
+fn sample_peak_memory(pid: u32) -> Option<u64> {
+    let children = fs::read_to_string(format!("/proc/{0}/task/{0}/children", pid)).unwrap_or_default();
+
+    children.split_whitespace()
+        .filter_map(|child| child.parse().ok())
+        .filter_map(sample_peak_memory)
+        .chain(own_peak_memory(pid))
+        .max()
+}
+
+fn own_peak_memory(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let kilobytes = status.lines()
+        .find(|line| line.starts_with("VmHWM:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+fn read_in_background<R: Read + Send + 'static>(reader: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+
+        let mut discarded = 0;
+
+        if let Some(mut reader) = reader {
+            let _ = reader.by_ref().take(MAX_OUTPUT_BYTES).read_to_end(&mut output);
+            // Keeps draining the pipe, so the process doesn't block until it times out
+            discarded = io::copy(&mut reader, &mut io::sink()).unwrap_or(0);
+        }
+
+        let mut output = String::from_utf8_lossy(&output).into_owned();
+
+        if discarded > 0 {
+            output.push_str(&format!("\n[{} more bytes of output discarded]\n", discarded));
+        }
+
+        output
+    })
+}
@@ -0,0 +1,41 @@
+use {Edition, ExecuteResponse};
+use std::borrow::Cow;
+use reqwest::{Client, Error};
+
+pub fn expand_macros(client: &Client, req: &Request) -> Result<ExecuteResponse, Error> {
+    let resp = client
+        .post("https://play.rust-lang.org/macro-expansion")
+        .json(req)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(resp)
+}
+
+#[derive(Serialize,Debug)]
+pub struct Request<'a> {
+    code: Cow<'a, str>,
+    edition: Edition,
+}
+
+impl<'a> Request<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(code: S) -> Self {
+        Self {
+            code: code.into(),
+            edition: Edition::E2018,
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn edition(&self) -> Edition {
+        self.edition
+    }
+
+    pub fn set_edition(&mut self, edition: Edition) {
+        self.edition = edition;
+    }
+}
@@ -0,0 +1,31 @@
+use ExecuteResponse;
+use std::borrow::Cow;
+use reqwest::{Client, Error};
+
+pub fn miri(client: &Client, req: &Request) -> Result<ExecuteResponse, Error> {
+    let resp = client
+        .post("https://play.rust-lang.org/miri")
+        .json(req)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(resp)
+}
+
+#[derive(Serialize,Debug)]
+pub struct Request<'a> {
+    code: Cow<'a, str>,
+}
+
+impl<'a> Request<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(code: S) -> Self {
+        Self {
+            code: code.into(),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
@@ -19,6 +19,24 @@ pub fn paste<S: AsRef<str>>(client: &Client, text: S, channel: Channel, mode: Mo
     Ok(url)
 }
 
+/// Fetches the gist with the given id.
+pub fn gist(client: &Client, id: &str) -> Result<Gist, Error> {
+    let gist = client
+        .get(&format!("https://play.rust-lang.org/meta/gist/{}", id))
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(gist)
+}
+
+#[derive(Deserialize,Debug)]
+pub struct Gist {
+    pub id: String,
+    pub url: String,
+    pub code: String,
+}
+
 #[derive(Serialize)]
 struct Request<'a> {
     code: &'a str,
@@ -0,0 +1,151 @@
+//! Feeds stdin to programs run on the playground, which has no way to pass it.
+
+/// Wraps `code` so its `main` runs with `input` as stdin.
+/// The code is left alone if it has no `main` function.
+pub fn wrap(code: &str, input: &str) -> String {
+    let main = match find_main(code) {
+        Some(main) => main,
+        None => return code.to_string(),
+    };
+
+    let code = format!("{}fn __playbot_main{}", &code[..main], &code[main + "fn main".len()..]);
+
+    format!(include_str!("../stdin_template.rs"),
+        code = code,
+        input = input,
+    )
+}
+
+/// Finds the start of the top level `fn main` of `code`,
+/// skipping functions of the same name in modules, impls or other functions.
+fn find_main(code: &str) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        i = match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |end| i + end)
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'*') => skip_block_comment(bytes, i),
+            b'"' => skip_string(bytes, i),
+            b'r' if is_raw_string_start(bytes, i) => skip_raw_string(bytes, i),
+            b'\'' => skip_char_or_lifetime(code, i),
+            b'{' => {
+                depth += 1;
+                i + 1
+            },
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                i + 1
+            },
+            b'f' if depth == 0 && is_main(code, i) => return Some(i),
+            _ => i + 1,
+        };
+    }
+
+    None
+}
+
+/// Whether `fn main(` starts at `start`.
+fn is_main(code: &str, start: usize) -> bool {
+    if !code[start..].starts_with("fn main") {
+        return false;
+    }
+
+    let before = code[..start].chars().next_back();
+    let after = code[start + "fn main".len()..].trim_left().chars().next();
+
+    !before.map_or(false, is_ident_char) && after == Some('(')
+}
+
+fn skip_block_comment(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+
+    while i + 1 < bytes.len() {
+        match (bytes[i], bytes[i + 1]) {
+            (b'/', b'*') => {
+                depth += 1;
+                i += 2;
+            },
+            (b'*', b'/') => {
+                depth -= 1;
+                i += 2;
+
+                if depth == 0 {
+                    return i;
+                }
+            },
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+fn is_raw_string_start(bytes: &[u8], start: usize) -> bool {
+    let prefix = if start > 0 && bytes[start - 1] == b'b' { start - 1 } else { start };
+
+    if prefix > 0 && is_ident_char(bytes[prefix - 1] as char) {
+        return false;
+    }
+
+    let hashes = bytes[start + 1..].iter().take_while(|&&b| b == b'#').count();
+
+    bytes.get(start + 1 + hashes) == Some(&b'"')
+}
+
+fn skip_raw_string(bytes: &[u8], start: usize) -> usize {
+    let hashes = bytes[start + 1..].iter().take_while(|&&b| b == b'#').count();
+    let mut i = start + 1 + hashes + 1;
+
+    while i < bytes.len() {
+        if bytes[i] == b'"' && bytes[i + 1..].iter().take(hashes).filter(|&&b| b == b'#').count() == hashes {
+            return i + 1 + hashes;
+        }
+
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+/// Distinguishes char literals (`'{'`, `'\\n'`) from lifetimes and labels (`'a`).
+fn skip_char_or_lifetime(code: &str, start: usize) -> usize {
+    let bytes = code.as_bytes();
+
+    if bytes.get(start + 1) == Some(&b'\\') {
+        return bytes[start + 2..].iter()
+            .position(|&b| b == b'\'')
+            .map(|pos| start + 2 + pos + 1)
+            .unwrap_or(bytes.len());
+    }
+
+    let char_len = code[start + 1..].chars().next().map(char::len_utf8).unwrap_or(0);
+
+    if bytes.get(start + 1 + char_len) == Some(&b'\'') {
+        start + 1 + char_len + 1
+    } else {
+        start + 1
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -11,7 +11,7 @@ pub fn version(client: &Client, channel: Channel) -> Result<Version, Error> {
     Ok(resp)
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize,Clone)]
 pub struct Version {
     pub date: String,
     pub hash: String,
@@ -0,0 +1,54 @@
+{code}
+
+mod __playbot_stdin {{
+    use std::fs::File;
+    use std::io::Write;
+    use std::os::unix::io::FromRawFd;
+    use std::thread;
+
+    extern "C" {{
+        fn pipe(fds: *mut i32) -> i32;
+        fn dup2(old: i32, new: i32) -> i32;
+        fn close(fd: i32) -> i32;
+    }}
+
+    /// Replaces stdin with a pipe that is fed `input`.
+    pub fn feed(input: &'static str) {{
+        let mut fds = [0; 2];
+
+        unsafe {{
+            assert_eq!(pipe(fds.as_mut_ptr()), 0, "failed to create the stdin pipe");
+            assert!(dup2(fds[0], 0) != -1, "failed to replace stdin");
+            close(fds[0]);
+        }}
+
+        let mut writer = unsafe {{ File::from_raw_fd(fds[1]) }};
+
+        thread::spawn(move || {{
+            let _ = writer.write_all(input.as_bytes());
+        }});
+    }}
+
+    /// Exits like `main` would with the result of the original `main`.
+    pub trait Exit {{
+        fn exit(self);
+    }}
+
+    impl Exit for () {{
+        fn exit(self) {{}}
+    }}
+
+    impl<E: ::std::fmt::Debug> Exit for Result<(), E> {{
+        fn exit(self) {{
+            if let Err(e) = self {{
+                eprintln!("Error: {{:?}}", e);
+                ::std::process::exit(1);
+            }}
+        }}
+    }}
+}}
+
+fn main() {{
+    __playbot_stdin::feed({input:?});
+    __playbot_stdin::Exit::exit(__playbot_main());
+}}
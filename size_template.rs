@@ -0,0 +1,13 @@
+{crate_attrs}
+{prelude}
+{items}
+fn main() {{
+    match {{
+        {code}
+    }} {{
+        value => println!("size: {{}}, align: {{}}",
+            ::std::mem::size_of_val(&value),
+            ::std::mem::align_of_val(&value),
+        ),
+    }}
+}}
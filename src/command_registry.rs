@@ -1,57 +1,121 @@
 use serenity::framework::Framework;
 use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, MessageId};
 use serenity::prelude::Context as SerenityContext;
 
 use threadpool::ThreadPool;
 
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::ops::Deref;
 use super::{Context, Flow, Command};
+use context::ReplySettings;
+use paste::Paste;
+use evaluations::EvaluationQueue;
+use replies::ReplyLog;
+use turns::Turns;
 use std::iter;
 
+const REPLY_LOG_CAPACITY: usize = 1000;
+/// Messages are handled in parallel, evaluations wait in their queue instead
+const DISPATCH_THREADS: usize = 32;
+
 pub struct CommandRegistry {
     command_prefix: String,
-    named_handlers: HashMap<String, Box<FnMut(&Context, &[&str]) -> Flow + Send>>,
-    fallback_handlers: Vec<Box<FnMut(&Context) -> Flow + Send>>,
+    named_handlers: HashMap<String, Box<Fn(&Context, &[&str]) -> Flow + Send + Sync>>,
+    fallback_handlers: Vec<Box<Fn(&Context) -> Flow + Send + Sync>>,
+    replies: Mutex<ReplyLog>,
+    reply_settings: ReplySettings,
+    paste: Arc<Paste>,
+    evaluations: EvaluationQueue,
+    turns: Turns,
+    pool: Mutex<ThreadPool>,
 }
 
 impl CommandRegistry {
-    pub fn new(command_prefix: impl Into<String>) -> Self {
+    pub fn new(
+        command_prefix: impl Into<String>,
+        reply_settings: ReplySettings,
+        paste: Arc<Paste>,
+        max_evaluations: usize,
+    ) -> Self {
         Self {
             command_prefix: command_prefix.into(),
             named_handlers: HashMap::new(),
             fallback_handlers: Vec::new(),
+            replies: Mutex::new(ReplyLog::new(REPLY_LOG_CAPACITY)),
+            reply_settings,
+            paste,
+            evaluations: EvaluationQueue::new(max_evaluations),
+            turns: Turns::new(),
+            pool: Mutex::new(ThreadPool::new(DISPATCH_THREADS)),
         }
     }
 
     pub fn set_named_handler(
         &mut self,
         name: impl Into<String>,
-        handler: impl Fn(&Context, &[&str]) -> Flow + Send + 'static,
+        handler: impl Fn(&Context, &[&str]) -> Flow + Send + Sync + 'static,
     ) {
         self.named_handlers.insert(name.into(), Box::new(handler));  
     }
 
     pub fn add_fallback_handler(
         &mut self,
-        handler: impl Fn(&Context) -> Flow + Send + 'static,
+        handler: impl Fn(&Context) -> Flow + Send + Sync + 'static,
     ) {
         self.fallback_handlers.push(Box::new(handler));
     }
 
-    pub fn handle_message(&mut self, pool: &ThreadPool, message: &Message) {
-        let context = match Context::new(&pool, &message) {
+    /// Handles a new message once it is `turn`'s turn.
+    pub fn handle_message(&self, pool: &ThreadPool, message: &Message, turn: u64) {
+        let _turn = self.turns.wait(message.id, turn);
+        self.handle(pool, message, Vec::new());
+    }
+
+    /// Handles an edited message again once it is `turn`'s turn,
+    /// editing the previous replies in place.
+    pub fn handle_edit(&self, pool: &ThreadPool, message: &Message, turn: u64) {
+        let _turn = self.turns.wait(message.id, turn);
+        let previous_replies = self.replies.lock().unwrap().take(message.id);
+        self.handle(pool, message, previous_replies);
+    }
+
+    /// Deletes the replies to a deleted message once it is `turn`'s turn.
+    pub fn handle_delete(&self, channel_id: ChannelId, message_id: MessageId, turn: u64) {
+        let _turn = self.turns.wait(message_id, turn);
+        let replies = self.replies.lock().unwrap().take(message_id);
+        delete_messages(channel_id, replies);
+    }
+
+    fn handle(&self, pool: &ThreadPool, message: &Message, previous_replies: Vec<MessageId>) {
+        let context = match Context::new(&pool, &*self.paste, &self.evaluations, &message, &self.reply_settings) {
             Some(context) => context,
-            None => return,
+            None => return delete_messages(message.channel_id, previous_replies),
         };
 
+        context.reuse_replies(previous_replies);
+
+        self.dispatch(&context);
+
+        let (sent, unused) = context.take_replies();
+
+        delete_messages(message.channel_id, unused);
+
+        if !sent.is_empty() {
+            self.replies.lock().unwrap().insert(message.id, sent);
+        }
+    }
+
+    fn dispatch(&self, context: &Context) {
         if context.is_ctcp() {
             return;
         }
 
         // Handle the main context first
         if let Some(command) = Command::parse(&self.command_prefix, context.body()) {
-            if let Some(handler) = self.named_handlers.get_mut(command.name()) {
-                if handler(&context, command.args()) == Flow::Break {
+            if let Some(handler) = self.named_handlers.get(command.name()) {
+                if handler(context, command.args()) == Flow::Break {
                     return;
                 }
             }
@@ -62,7 +126,7 @@ impl CommandRegistry {
         let mut any_inline_command_succeded = false;
         for context in contexts.take(3) {
             if let Some(command) = Command::parse(&self.command_prefix, context.body()) {
-                if let Some(handler) = self.named_handlers.get_mut(command.name()) {
+                if let Some(handler) = self.named_handlers.get(command.name()) {
                     if handler(&context, command.args()) == Flow::Break {
                         any_inline_command_succeded = true;
                     }
@@ -74,17 +138,76 @@ impl CommandRegistry {
             return;
         }
 
-        for handler in &mut self.fallback_handlers {
-            if handler(&context) == Flow::Break {
+        for handler in &self.fallback_handlers {
+            if handler(context) == Flow::Break {
                 return;
             }
         }
     }
 }
 
+fn delete_messages(channel_id: ChannelId, messages: Vec<MessageId>) {
+    for message_id in messages {
+        if let Err(e) = channel_id.delete_message(message_id) {
+            eprintln!("Failed to delete reply {}: {:?}", message_id, e);
+        }
+    }
+}
+
+/// A `CommandRegistry` shared between the framework and the event handler.
+#[derive(Clone)]
+pub struct SharedRegistry(Arc<CommandRegistry>);
 
-impl Framework for CommandRegistry {
-    fn dispatch(&mut self, _: SerenityContext, m: Message, t: &ThreadPool) {
-        self.handle_message(t, &m);
+impl SharedRegistry {
+    pub fn new(registry: CommandRegistry) -> Self {
+        SharedRegistry(Arc::new(registry))
     }
-}
\ No newline at end of file
+
+    /// Handles `message` on the registry's thread pool.
+    pub fn dispatch_message(&self, message: Message) {
+        let turn = self.turns.take(message.id);
+        self.spawn(move |registry, pool| registry.handle_message(pool, &message, turn));
+    }
+
+    /// Handles the edited `message` on the registry's thread pool,
+    /// after the previous version is answered.
+    /// Evaluations of the previous version that still wait in the queue are cancelled.
+    pub fn dispatch_edit(&self, message: Message) {
+        self.evaluations.cancel(message.id);
+        let turn = self.turns.take(message.id);
+        self.spawn(move |registry, pool| registry.handle_edit(pool, &message, turn));
+    }
+
+    /// Deletes the replies to a deleted message on the registry's thread pool,
+    /// after the message is answered.
+    /// Its evaluations that still wait in the queue are cancelled.
+    pub fn dispatch_delete(&self, channel_id: ChannelId, message_id: MessageId) {
+        self.evaluations.cancel(message_id);
+        let turn = self.turns.take(message_id);
+        self.spawn(move |registry, _| registry.handle_delete(channel_id, message_id, turn));
+    }
+
+    fn spawn<F>(&self, handle: F)
+    where
+        F: FnOnce(&CommandRegistry, &ThreadPool) + Send + 'static,
+    {
+        let registry = self.0.clone();
+        let pool = self.pool.lock().unwrap().clone();
+
+        pool.clone().execute(move || handle(&registry, &pool));
+    }
+}
+
+impl Deref for SharedRegistry {
+    type Target = CommandRegistry;
+
+    fn deref(&self) -> &CommandRegistry {
+        &self.0
+    }
+}
+
+impl Framework for SharedRegistry {
+    fn dispatch(&mut self, _: SerenityContext, m: Message, _: &ThreadPool) {
+        self.dispatch_message(m);
+    }
+}
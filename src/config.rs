@@ -1,11 +1,57 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::time::Duration;
 
 use toml;
 use failure::{Error, err_msg};
+use serenity::model::id::ChannelId;
+use context::{ReplyBudget, ReplySettings, ReplyStyle, ReplyStyles};
+use paste::PasteService;
+use http::Timeouts;
+use playground::local::Sandbox;
+
+const DEFAULT_PRELUDE: &[&str] = &[
+    "std::collections::HashMap",
+    "std::collections::HashSet",
+    "std::collections::BTreeMap",
+    "std::collections::BTreeSet",
+    "std::collections::VecDeque",
+    "std::collections::BinaryHeap",
+    "std::rc::Rc",
+    "std::cell::Cell",
+    "std::cell::RefCell",
+    "std::sync::Arc",
+    "std::sync::Mutex",
+    "std::fmt",
+    "std::mem",
+    "std::iter",
+    "std::io",
+    "std::io::Read",
+    "std::io::Write",
+    "std::io::BufRead",
+];
+
+const DEFAULT_BLOCKED_PREFIXES: &[&str] = &["!", "?", ".", "$", ";", "%"];
+
+const DEFAULT_PASTE_DIRECTORY: &str = "pastes";
+const DEFAULT_PASTE_EXPIRY_HOURS: usize = 24 * 7;
+const DEFAULT_CACHE_TTL_SECS: usize = 5 * 60;
+const DEFAULT_MAX_EVALUATIONS: usize = 4;
+const DEFAULT_PLAYGROUND_TIMEOUT_SECS: usize = 30;
+const DEFAULT_CRATESIO_TIMEOUT_SECS: usize = 10;
+const DEFAULT_CONNECT_TIMEOUT_SECS: usize = 5;
+const DEFAULT_NSJAIL: &str = "nsjail";
 
 pub struct Config {
     token: String,
+    prelude: Vec<String>,
+    reply_settings: ReplySettings,
+    paste_service: PasteService,
+    cache_ttl: Duration,
+    max_evaluations: usize,
+    playground_timeouts: Timeouts,
+    cratesio_timeouts: Timeouts,
+    local_sandbox: Option<Sandbox>,
 }
 
 impl Config {
@@ -17,12 +63,238 @@ impl Config {
             None => return Err(err_msg("field `token` not found!")),
         };       
 
+        let prelude = match file.get("prelude") {
+            Some(prelude) => parse_strings("prelude", prelude)?,
+            None => DEFAULT_PRELUDE.iter().map(|&path| path.to_string()).collect(),
+        };
+
+        let default_reply_style = match file.get("reply_style") {
+            Some(style) => parse_reply_style(style)?,
+            None => ReplyStyle::default(),
+        };
+
+        let mut reply_styles = ReplyStyles::new(default_reply_style);
+
+        if let Some(channels) = file.get("channels") {
+            let channels = channels.as_table()
+                .ok_or_else(|| err_msg("field `channels` must be a table!"))?;
+
+            for (id, channel) in channels {
+                let id = id.parse::<u64>()
+                    .map_err(|_| err_msg(format!("invalid channel id `{}`!", id)))?;
+
+                if let Some(style) = channel.get("reply_style") {
+                    reply_styles.set(ChannelId(id), parse_reply_style(style)?);
+                }
+            }
+        }
+
+        let mut reply_budget = ReplyBudget::default();
+
+        if let Some(max_lines) = file.get("max_reply_lines") {
+            reply_budget.max_lines = parse_count("max_reply_lines", max_lines)?;
+        }
+
+        if let Some(max_messages) = file.get("max_reply_messages") {
+            reply_budget.max_messages = parse_count("max_reply_messages", max_messages)?;
+        }
+
+        let blocked_prefixes = match file.get("blocked_prefixes") {
+            Some(prefixes) => parse_strings("blocked_prefixes", prefixes)?,
+            None => DEFAULT_BLOCKED_PREFIXES.iter().map(|&prefix| prefix.to_string()).collect(),
+        };
+
+        let paste_service = match file.get("paste") {
+            Some(paste) => parse_paste_service(paste)?,
+            None => PasteService::default(),
+        };
+
+        let cache_ttl_secs = match file.get("cache_ttl_secs") {
+            Some(secs) => parse_count("cache_ttl_secs", secs)?,
+            None => DEFAULT_CACHE_TTL_SECS,
+        };
+
+        let max_evaluations = match file.get("max_evaluations") {
+            Some(max) => parse_count("max_evaluations", max)?,
+            None => DEFAULT_MAX_EVALUATIONS,
+        };
+
+        let playground_timeout_secs = match file.get("playground_timeout_secs") {
+            Some(secs) => parse_count("playground_timeout_secs", secs)?,
+            None => DEFAULT_PLAYGROUND_TIMEOUT_SECS,
+        };
+
+        let cratesio_timeout_secs = match file.get("cratesio_timeout_secs") {
+            Some(secs) => parse_count("cratesio_timeout_secs", secs)?,
+            None => DEFAULT_CRATESIO_TIMEOUT_SECS,
+        };
+
+        let connect_timeout_secs = match file.get("connect_timeout_secs") {
+            Some(secs) => parse_count("connect_timeout_secs", secs)?,
+            None => DEFAULT_CONNECT_TIMEOUT_SECS,
+        };
+        let connect_timeout = Duration::from_secs(connect_timeout_secs as u64);
+
+        let local_sandbox = match file.get("local") {
+            Some(local) => parse_local_sandbox(local)?,
+            None => None,
+        };
+
         Ok(Config {
-            token
+            token,
+            prelude,
+            reply_settings: ReplySettings {
+                styles: reply_styles,
+                budget: reply_budget,
+                blocked_prefixes,
+            },
+            paste_service,
+            cache_ttl: Duration::from_secs(cache_ttl_secs as u64),
+            max_evaluations,
+            playground_timeouts: Timeouts {
+                connect: connect_timeout,
+                total: Duration::from_secs(playground_timeout_secs as u64),
+            },
+            cratesio_timeouts: Timeouts {
+                connect: connect_timeout,
+                total: Duration::from_secs(cratesio_timeout_secs as u64),
+            },
+            local_sandbox,
         })
     }
 
     pub fn token(&self) -> &str {
         &self.token
     }
+
+    /// Paths that are imported into wrapped code
+    pub fn prelude(&self) -> &[String] {
+        &self.prelude
+    }
+
+    pub fn reply_settings(&self) -> &ReplySettings {
+        &self.reply_settings
+    }
+
+    /// Where long outputs are pasted
+    pub fn paste_service(&self) -> &PasteService {
+        &self.paste_service
+    }
+
+    /// How long playground responses are reused for identical requests
+    pub fn cache_ttl(&self) -> Duration {
+        self.cache_ttl
+    }
+
+    /// How many evaluations may run at once, others wait in a queue
+    pub fn max_evaluations(&self) -> usize {
+        self.max_evaluations
+    }
+
+    /// After how long requests to the playground and paste services fail
+    pub fn playground_timeouts(&self) -> Timeouts {
+        self.playground_timeouts
+    }
+
+    /// After how long requests to crates.io fail
+    pub fn cratesio_timeouts(&self) -> Timeouts {
+        self.cratesio_timeouts
+    }
+
+    /// The sandbox for running code with local toolchains, unless they are disabled
+    pub fn local_sandbox(&self) -> Option<&Sandbox> {
+        self.local_sandbox.as_ref()
+    }
+}
+
+fn parse_strings(field: &str, strings: &toml::Value) -> Result<Vec<String>, Error> {
+    strings.as_array()
+        .ok_or_else(|| err_msg(format!("field `{}` must be an array!", field)))?
+        .iter()
+        .map(|string| string.as_str()
+            .map(String::from)
+            .ok_or_else(|| err_msg(format!("field `{}` must only contain strings!", field))))
+        .collect()
+}
+
+fn parse_count(field: &str, count: &toml::Value) -> Result<usize, Error> {
+    match count.as_integer() {
+        Some(count) if count > 0 => Ok(count as usize),
+        _ => Err(err_msg(format!("field `{}` must be a positive integer!", field))),
+    }
+}
+
+fn parse_reply_style(style: &toml::Value) -> Result<ReplyStyle, Error> {
+    style.as_str()
+        .ok_or_else(|| err_msg("field `reply_style` must be a string!"))?
+        .parse()
+}
+
+fn parse_paste_service(paste: &toml::Value) -> Result<PasteService, Error> {
+    let string = |field: &str| match paste.get(field) {
+        Some(value) => value.as_str()
+            .map(|value| Some(value.to_string()))
+            .ok_or_else(|| err_msg(format!("field `paste.{}` must be a string!", field))),
+        None => Ok(None),
+    };
+    let required = |field: &str| string(field)?
+        .ok_or_else(|| err_msg(format!("field `paste.{}` not found!", field)));
+
+    let service = string("service")?.unwrap_or_else(|| "gist".to_string());
+
+    match service.as_str() {
+        "gist" => Ok(PasteService::Gist),
+        "local" => {
+            let expiry_hours = match paste.get("expiry_hours") {
+                Some(hours) => parse_count("paste.expiry_hours", hours)?,
+                None => DEFAULT_PASTE_EXPIRY_HOURS,
+            };
+
+            Ok(PasteService::Local {
+                address: required("address")?,
+                url: required("url")?,
+                directory: PathBuf::from(string("directory")?
+                    .unwrap_or_else(|| DEFAULT_PASTE_DIRECTORY.to_string())),
+                expiry: Duration::from_secs(expiry_hours as u64 * 60 * 60),
+            })
+        },
+        "post" => Ok(PasteService::Post {
+            url: required("url")?,
+            field: string("field")?,
+        }),
+        _ => Err(err_msg(format!("Unknown paste service '{}'", service))),
+    }
+}
+
+fn parse_local_sandbox(local: &toml::Value) -> Result<Option<Sandbox>, Error> {
+    let enabled = match local.get("enabled") {
+        Some(enabled) => enabled.as_bool()
+            .ok_or_else(|| err_msg("field `local.enabled` must be a boolean!"))?,
+        None => false,
+    };
+
+    if !enabled {
+        return Ok(None);
+    }
+
+    let nsjail = match local.get("nsjail") {
+        Some(nsjail) => nsjail.as_str()
+            .ok_or_else(|| err_msg("field `local.nsjail` must be a string!"))?,
+        None => DEFAULT_NSJAIL,
+    };
+
+    let mut sandbox = Sandbox::new(nsjail);
+
+    if let Some(paths) = local.get("read_only") {
+        sandbox.set_read_only(parse_strings("local.read_only", paths)?
+            .into_iter()
+            .map(PathBuf::from)
+            .collect());
+    }
+
+    if let Some(memory_mb) = local.get("memory_mb") {
+        sandbox.set_memory_limit(parse_count("local.memory_mb", memory_mb)? as u64);
+    }
+
+    Ok(Some(sandbox))
 }
\ No newline at end of file
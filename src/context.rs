@@ -1,19 +1,158 @@
 use serenity;
 use serenity::model::{
     channel::Message,
-    id::{ChannelId, UserId},
+    id::{ChannelId, MessageId, UserId},
 };
 
 use threadpool::ThreadPool;
+use playground::{Channel, Mode};
+use paste::Paste;
+use evaluations::{EvaluationQueue, Slot};
 
 use regex::Regex;
+use failure::{Error, err_msg};
 use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::borrow::Cow;
 
-type SendFn = fn(&ThreadPool, ChannelId, &str) -> serenity::Result<()>;
+type SendFn = fn(&ThreadPool, ChannelId, &str) -> serenity::Result<Message>;
+
+lazy_static! {
+    static ref MASS_MENTION: Regex = Regex::new(r"@(everyone|here)").unwrap();
+    static ref PING: Regex = Regex::new(r"<@([!&]?[0-9]+)>").unwrap();
+}
+
+/// The chat service a message came from.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Backend {
+    Discord,
+}
+
+impl Backend {
+    /// Whether the backend renders markdown, e.g. code blocks.
+    pub fn supports_formatting(&self) -> bool {
+        match *self {
+            Backend::Discord => true,
+        }
+    }
+
+    /// Whether a single message can span multiple lines.
+    pub fn supports_multiline(&self) -> bool {
+        match *self {
+            Backend::Discord => true,
+        }
+    }
+
+    /// The maximum length of a message in bytes.
+    pub fn max_message_len(&self) -> usize {
+        match *self {
+            Backend::Discord => 2000,
+        }
+    }
+}
+
+/// Limits after which a reply is pasted instead.
+#[derive(Copy, Clone, Debug)]
+pub struct ReplyBudget {
+    pub max_lines: usize,
+    pub max_messages: usize,
+}
+
+impl Default for ReplyBudget {
+    fn default() -> Self {
+        Self {
+            max_lines: 10,
+            max_messages: 3,
+        }
+    }
+}
+
+/// How replies refer to the message they answer.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ReplyStyle {
+    /// Just the reply
+    Plain,
+    /// Prefixed with the nickname of the requester, IRC style
+    Nickname,
+    /// Prefixed with a mention of the requester
+    Mention,
+    /// As an embed naming the requester
+    Embed,
+}
+
+impl Default for ReplyStyle {
+    fn default() -> Self {
+        ReplyStyle::Plain
+    }
+}
+
+impl FromStr for ReplyStyle {
+    type Err = Error;
+
+    fn from_str(style: &str) -> Result<Self, Error> {
+        match style {
+            "plain" => Ok(ReplyStyle::Plain),
+            "nickname" => Ok(ReplyStyle::Nickname),
+            "mention" => Ok(ReplyStyle::Mention),
+            "embed" => Ok(ReplyStyle::Embed),
+            _ => Err(err_msg(format!("Unknown reply style '{}'", style))),
+        }
+    }
+}
+
+/// The reply style of each channel.
+#[derive(Clone, Default)]
+pub struct ReplyStyles {
+    default: ReplyStyle,
+    channels: HashMap<ChannelId, ReplyStyle>,
+}
+
+impl ReplyStyles {
+    pub fn new(default: ReplyStyle) -> Self {
+        Self {
+            default,
+            channels: HashMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, channel: ChannelId, style: ReplyStyle) {
+        self.channels.insert(channel, style);
+    }
+
+    pub fn get(&self, channel: ChannelId) -> ReplyStyle {
+        self.channels.get(&channel).cloned().unwrap_or(self.default)
+    }
+}
+
+/// Settings for how the bot replies, shared by all contexts.
+#[derive(Clone, Default)]
+pub struct ReplySettings {
+    pub styles: ReplyStyles,
+    pub budget: ReplyBudget,
+    /// Command prefixes of other bots that replies must not start with
+    pub blocked_prefixes: Vec<String>,
+}
+
+/// The messages sent in reply to the source message.
+#[derive(Default)]
+struct Replies {
+    /// Replies to a previous version of the message,
+    /// which get edited instead of sending new messages
+    reusable: VecDeque<MessageId>,
+    sent: Vec<MessageId>,
+}
 
 #[derive(Clone)]
 pub struct Context<'a> {
     body: &'a str,
+    backend: Backend,
+    reply_style: ReplyStyle,
+    reply_budget: ReplyBudget,
+    blocked_prefixes: &'a [String],
+    paste: &'a Paste,
+    evaluations: &'a EvaluationQueue,
     is_directly_addressed: bool,
     send_fn: SendFn,
     source: UserId,
@@ -22,10 +161,17 @@ pub struct Context<'a> {
     client: &'a Message,
     pool: &'a ThreadPool,
     current_nickname: Rc<String>,
+    replies: Rc<RefCell<Replies>>,
 }
 
 impl<'a> Context<'a> {
-    pub fn new(pool: &'a ThreadPool, message: &'a Message) -> Option<Self> {
+    pub fn new(
+        pool: &'a ThreadPool,
+        paste: &'a Paste,
+        evaluations: &'a EvaluationQueue,
+        message: &'a Message,
+        reply_settings: &'a ReplySettings,
+    ) -> Option<Self> {
         lazy_static! {
             static ref MENTION: Regex = Regex::new(r"<@[0-9]*>").unwrap();
         }
@@ -67,21 +213,47 @@ impl<'a> Context<'a> {
             }
         };
 
-        let send_fn: SendFn = |_pool, channel_id, msg| { channel_id.say(msg).map(|_| ()) }; 
+        let send_fn: SendFn = |_pool, channel_id, msg| { channel_id.say(msg) }; 
 
         Some(Self {
             client: message,
             pool,
             body,
+            backend: Backend::Discord,
+            reply_style: reply_settings.styles.get(target),
+            reply_budget: reply_settings.budget,
+            blocked_prefixes: &reply_settings.blocked_prefixes,
+            paste,
+            evaluations,
             send_fn,
             source,
             source_nickname,
             target,
             is_directly_addressed,
-            current_nickname
+            current_nickname,
+            replies: Rc::new(RefCell::new(Replies::default())),
         })
     }
 
+    /// Edit the given messages for the next replies instead of sending new ones.
+    pub fn reuse_replies(&self, replies: Vec<MessageId>) {
+        self.replies.borrow_mut().reusable.extend(replies);
+    }
+
+    /// Returns the messages sent (or edited) as replies,
+    /// as well as the reusable messages that weren't needed.
+    pub fn take_replies(&self) -> (Vec<MessageId>, Vec<MessageId>) {
+        let mut replies = self.replies.borrow_mut();
+        let sent = replies.sent.drain(..).collect();
+        let unused = replies.reusable.drain(..).collect();
+
+        (sent, unused)
+    }
+
+    pub fn message_id(&self) -> MessageId {
+        self.client.id
+    }
+
     pub fn body(&self) -> &'a str {
         self.body
     }
@@ -97,22 +269,217 @@ impl<'a> Context<'a> {
         false
     }
 
+    /// Replies with the lines of `message`, packed into as few messages as possible.
+    /// Lines that are too long for a message are truncated
+    /// and replies exceeding the reply budget are cut down to their first line,
+    /// both followed by a link to the full reply.
     pub fn reply<S: AsRef<str>>(&self, message: S) {
         let message = message.as_ref();
         eprintln!("Replying: {:?}", message);
-        for line in message.lines() {
-            if line.len() > 2000 {
-                let _ = (self.send_fn)(self.pool, self.target, "<<<message too long for irc>>>");
-                continue;
+
+        // Sanitize before measuring, sanitizing again when sending is a no-op
+        let message = &self.sanitize(message);
+        let max_len = self.max_message_len();
+        let lines = message.lines()
+            .map(|line| truncate(line, max_len))
+            .collect::<Vec<_>>();
+        let truncated = lines.iter().any(|line| match *line {
+            Cow::Owned(_) => true,
+            Cow::Borrowed(_) => false,
+        });
+        let over_budget = lines.len() > self.reply_budget.max_lines
+            || self.pack_lines(&lines).len() > self.reply_budget.max_messages;
+
+        let mut shown = if over_budget { lines[..1].to_vec() } else { lines };
+
+        if truncated || over_budget {
+            let link = match self.paste(message) {
+                Some(url) => format!("~~~ Full reply: {}", url),
+                None => "<<<reply too long>>>".to_string(),
+            };
+
+            shown.push(link.into());
+        }
+
+        for message in self.pack_lines(&shown) {
+            self.send(&message);
+        }
+    }
+
+    /// Packs lines into messages that fit the backend's limits.
+    /// The lines must not be longer than the maximum message length.
+    fn pack_lines<S: AsRef<str>>(&self, lines: &[S]) -> Vec<String> {
+        let max_len = self.max_message_len();
+        let mut messages = Vec::<String>::new();
+
+        for line in lines {
+            let line = line.as_ref();
+
+            if let Some(message) = messages.last_mut() {
+                if self.backend.supports_multiline() && message.len() + 1 + line.len() <= max_len {
+                    message.push('\n');
+                    message.push_str(line);
+                    continue;
+                }
+            }
+
+            messages.push(line.to_string());
+        }
+
+        messages
+    }
+
+    /// The maximum length of a message, without the reply style's decoration.
+    fn max_message_len(&self) -> usize {
+        let decoration_len = match self.reply_style {
+            ReplyStyle::Plain | ReplyStyle::Embed => 0,
+            ReplyStyle::Nickname => self.sanitize(self.source_nickname).len() + 2,
+            ReplyStyle::Mention => format!("<@{}>: ", self.source.0).len(),
+        };
+
+        self.backend.max_message_len() - decoration_len
+    }
+
+    /// Neutralizes mass mentions, pings and control characters in `text`,
+    /// and command prefixes of other bots where they could trigger them:
+    /// at the start of the message, or of every line if the backend sends lines separately.
+    /// Code blocks are left alone, as bots don't read commands from them.
+    fn sanitize(&self, text: &str) -> String {
+        let mut in_code_block = false;
+
+        text.lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let line = line.chars()
+                    .filter(|&c| !c.is_control() || c == '\t')
+                    .collect::<String>();
+                let line = MASS_MENTION.replace_all(&line, "@\u{200B}$1");
+                let line = PING.replace_all(&line, "<@\u{200B}$1>");
+                let guarded = !in_code_block && (i == 0 || !self.backend.supports_multiline());
+
+                if line.starts_with("```") {
+                    in_code_block = !in_code_block;
+                }
+
+                if guarded && self.blocked_prefixes.iter().any(|prefix| line.starts_with(prefix.as_str())) {
+                    format!("\u{200B}{}", line)
+                } else {
+                    line.into_owned()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Waits until an evaluation may run, telling the user their queue position
+    /// if that takes a while.
+    /// Returns `None` if the message got edited or deleted in the meantime.
+    pub fn evaluation_slot(&self) -> Option<Slot<'a>> {
+        self.evaluations.acquire(self.source, self.message_id(), |position| {
+            self.notice(&format!("Queued, position {}", position));
+        })
+    }
+
+    /// Sends `text` as a message that the next reply replaces.
+    fn notice(&self, text: &str) {
+        self.send(text);
+
+        let mut replies = self.replies.borrow_mut();
+
+        if let Some(id) = replies.sent.pop() {
+            replies.reusable.push_front(id);
+        }
+    }
+
+    /// Pastes `text`, returning the URL of the paste.
+    pub fn paste(&self, text: &str) -> Option<String> {
+        self.paste_code(text, Channel::Stable, Mode::Debug)
+    }
+
+    /// Pastes `text` with the playground settings it should be run with,
+    /// returning the URL of the paste.
+    pub fn paste_code(&self, text: &str, channel: Channel, mode: Mode) -> Option<String> {
+        match self.paste.paste(text, channel, mode) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                eprintln!("Failed to paste: {:?}", e);
+                None
+            },
+        }
+    }
+
+    fn send(&self, text: &str) {
+        let reusable = self.replies.borrow_mut().reusable.pop_front();
+        let text = self.sanitize(text);
+        // The nickname could contain mentions or a command prefix too
+        let nickname = self.sanitize(self.source_nickname);
+
+        let text = match self.reply_style {
+            ReplyStyle::Plain | ReplyStyle::Embed => text,
+            ReplyStyle::Nickname => format!("{}: {}", nickname, text),
+            ReplyStyle::Mention => format!("<@{}>: {}", self.source.0, text),
+        };
+        let footer = format!("Requested by {}", nickname);
+
+        let result = match (reusable, self.reply_style) {
+            (Some(id), ReplyStyle::Embed) => self.target.edit_message(id, |m| m
+                .embed(|e| e.description(&text).footer(|f| f.text(&footer)))),
+            (None, ReplyStyle::Embed) => self.target.send_message(|m| m
+                .embed(|e| e.description(&text).footer(|f| f.text(&footer)))),
+            (Some(id), _) => self.target.edit_message(id, |m| m.content(&text)),
+            (None, _) => (self.send_fn)(self.pool, self.target, &text),
+        };
+
+        match result {
+            Ok(message) => self.replies.borrow_mut().sent.push(message.id),
+            Err(e) => eprintln!("Failed to send reply: {:?}", e),
+        }
+    }
+
+    /// Replies with `code` as a single code block if the backend supports it,
+    /// line by line otherwise.
+    pub fn reply_code<S: AsRef<str>>(&self, lang: &str, code: S) {
+        let code = code.as_ref();
+
+        if !self.backend.supports_formatting() {
+            return self.reply(code);
+        }
+
+        eprintln!("Replying: {:?}", code);
+        // Prevent the code from closing the block early
+        let escaped = code.replace("```", "`\u{200B}``");
+        // Sanitize before measuring, sanitizing again when sending is a no-op
+        let message = self.sanitize(&format!("```{}\n{}\n```", lang, escaped));
+
+        if message.len() > self.max_message_len() {
+            match self.paste(code) {
+                Some(url) => self.send(&format!("~~~ Code too long to display: {}", url)),
+                None => self.reply(code),
             }
-            let _ = (self.send_fn)(self.pool, self.target, line);
+
+            return;
         }
+
+        self.send(&message);
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// The limits after which replies are pasted instead.
+    pub fn reply_budget(&self) -> ReplyBudget {
+        self.reply_budget
     }
 
     pub fn source(&self) -> UserId {
         self.source
     }
 
+    pub fn target(&self) -> ChannelId {
+        self.target
+    }
+
     pub fn source_nickname(&self) -> &'a str {
         self.source_nickname
     }
@@ -139,3 +506,19 @@ impl<'a> Context<'a> {
         Box::new(contexts)
     }
 }
+
+/// Truncates `text` to at most `max_len` bytes, ending it with an ellipsis if it was too long.
+fn truncate(text: &str, max_len: usize) -> Cow<str> {
+    if text.len() <= max_len {
+        return text.into();
+    }
+
+    let ellipsis = "…";
+    let mut end = max_len.saturating_sub(ellipsis.len());
+
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("{}{}", &text[..end], ellipsis).into()
+}
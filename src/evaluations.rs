@@ -0,0 +1,154 @@
+use serenity::model::id::{MessageId, UserId};
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long an evaluation waits before the user is told about its queue position.
+const QUEUE_NOTICE_DELAY: Duration = Duration::from_secs(3);
+
+/// Limits how many evaluations run at once.
+/// Waiting evaluations of users with fewer running evaluations go first,
+/// so a single user can't take up all slots.
+pub struct EvaluationQueue {
+    limit: usize,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    running: HashMap<UserId, usize>,
+    waiting: Vec<Ticket>,
+    next_ticket: u64,
+}
+
+struct Ticket {
+    id: u64,
+    user: UserId,
+    message: MessageId,
+    cancelled: bool,
+}
+
+impl State {
+    fn running(&self, user: UserId) -> usize {
+        self.running.get(&user).cloned().unwrap_or(0)
+    }
+
+    /// The waiting tickets in the order they will run.
+    fn order(&self) -> Vec<u64> {
+        let mut tickets = self.waiting.iter()
+            .filter(|ticket| !ticket.cancelled)
+            .enumerate()
+            .map(|(arrival, ticket)| (self.running(ticket.user), arrival, ticket.id))
+            .collect::<Vec<_>>();
+
+        tickets.sort();
+        tickets.into_iter().map(|(_, _, id)| id).collect()
+    }
+}
+
+impl EvaluationQueue {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Waits for a free slot, calling `on_queued` with the queue position
+    /// if that takes a while.
+    /// Returns `None` if the evaluation got cancelled while waiting.
+    pub fn acquire<F>(&self, user: UserId, message: MessageId, on_queued: F) -> Option<Slot>
+    where
+        F: FnOnce(usize),
+    {
+        let queued_at = Instant::now();
+        let mut on_queued = Some(on_queued);
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_ticket;
+
+        state.next_ticket += 1;
+        state.waiting.push(Ticket { id, user, message, cancelled: false });
+
+        loop {
+            let index = state.waiting.iter().position(|ticket| ticket.id == id)
+                .expect("waiting ticket vanished");
+
+            if state.waiting[index].cancelled {
+                state.waiting.remove(index);
+                return None;
+            }
+
+            let running = state.running.values().sum::<usize>();
+            let order = state.order();
+
+            if running < self.limit && order.first() == Some(&id) {
+                state.waiting.remove(index);
+                *state.running.entry(user).or_insert(0) += 1;
+                // There might be room for the next ticket as well
+                self.changed.notify_all();
+
+                return Some(Slot { queue: self, user });
+            }
+
+            let waited = queued_at.elapsed();
+
+            if waited < QUEUE_NOTICE_DELAY {
+                state = self.changed.wait_timeout(state, QUEUE_NOTICE_DELAY - waited).unwrap().0;
+                continue;
+            }
+
+            if let Some(on_queued) = on_queued.take() {
+                let position = order.iter().position(|&ticket| ticket == id).unwrap_or(0) + 1;
+
+                drop(state);
+                on_queued(position);
+                state = self.state.lock().unwrap();
+                continue;
+            }
+
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Cancels the waiting evaluations of `message`.
+    pub fn cancel(&self, message: MessageId) {
+        let mut state = self.state.lock().unwrap();
+
+        for ticket in &mut state.waiting {
+            if ticket.message == message {
+                ticket.cancelled = true;
+            }
+        }
+
+        self.changed.notify_all();
+    }
+
+    fn release(&self, user: UserId) {
+        let mut state = self.state.lock().unwrap();
+        let remaining = {
+            let running = state.running.entry(user).or_insert(1);
+            *running -= 1;
+            *running
+        };
+
+        if remaining == 0 {
+            state.running.remove(&user);
+        }
+
+        self.changed.notify_all();
+    }
+}
+
+/// A running evaluation, freeing its slot when dropped.
+pub struct Slot<'a> {
+    queue: &'a EvaluationQueue,
+    user: UserId,
+}
+
+impl<'a> Drop for Slot<'a> {
+    fn drop(&mut self) {
+        self.queue.release(self.user);
+    }
+}
@@ -0,0 +1,60 @@
+use reqwest::{Client, Error};
+use std::thread;
+use std::time::Duration;
+
+/// Delays before retrying a failed idempotent request, in milliseconds.
+const RETRY_DELAYS: &[u64] = &[500, 1000, 2000];
+
+/// After how long requests to a service fail.
+#[derive(Copy, Clone, Debug)]
+pub struct Timeouts {
+    /// For establishing the connection
+    pub connect: Duration,
+    /// For the whole request, including reading the response
+    pub total: Duration,
+}
+
+/// Creates a client whose requests fail after the `timeouts`.
+/// Panics like `Client::new` if the TLS backend can't be initialized.
+pub fn client(timeouts: Timeouts) -> Client {
+    Client::builder()
+        .connect_timeout(timeouts.connect)
+        .timeout(timeouts.total)
+        .build()
+        .expect("failed to create HTTP client")
+}
+
+/// A reply for a failed request, telling timeouts apart from other failures.
+pub fn error_reply(action: &str, error: &Error) -> String {
+    if error.is_timeout() {
+        format!("{}: timed out", action)
+    } else {
+        action.to_string()
+    }
+}
+
+/// Runs an idempotent `request`, retrying with increasing delays
+/// if the connection failed or the server had an error.
+/// Timeouts aren't retried, as they already took as long as the user should wait.
+pub fn retry<T, F>(mut request: F) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+{
+    let mut delays = RETRY_DELAYS.iter();
+
+    loop {
+        let result = request();
+        let retryable = match result {
+            Ok(_) => false,
+            Err(ref e) => !e.is_timeout() && (e.is_http() || e.is_server_error()),
+        };
+
+        match delays.next() {
+            Some(&delay) if retryable => {
+                eprintln!("Retrying failed request in {}ms", delay);
+                thread::sleep(Duration::from_millis(delay));
+            },
+            _ => return result,
+        }
+    }
+}
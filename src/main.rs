@@ -1,5 +1,6 @@
 #![feature(box_patterns)]
 #![feature(option_filter)]
+#[macro_use]
 extern crate failure;
 extern crate serenity;
 extern crate toml;
@@ -15,46 +16,87 @@ extern crate playground;
 extern crate cratesio;
 
 use std::thread;
+use std::process;
+use std::sync::Arc;
 use chrono::{
     prelude::*,
     Duration,
 };
-use serenity::prelude::{Client, EventHandler};
+use serenity::prelude::{Client, EventHandler, Context as SerenityContext};
+use serenity::model::event::MessageUpdateEvent;
+use serenity::model::id::{ChannelId, MessageId};
 use failure::{Error, SyncFailure};
 use self::{
     context::Context,
     command::Command,
-    command_registry::CommandRegistry,
+    command_registry::{CommandRegistry, SharedRegistry},
+    config::Config,
 };
 use module::Module;
-use config::Config;
+use paste::Paste;
 
 mod context;
 mod command;
 mod command_registry;
 mod module;
 mod config;
+mod replies;
+mod paste;
+mod evaluations;
+mod turns;
+mod http;
 // mod codedb;
 
-struct Handler;
+struct Handler {
+    commands: SharedRegistry,
+}
+
+impl EventHandler for Handler {
+    fn message_update(&self, _: SerenityContext, update: MessageUpdateEvent) {
+        // Updates without content are e.g. embeds being added
+        if update.content.is_none() {
+            return;
+        }
+
+        let message = match update.channel_id.message(update.id) {
+            Ok(message) => message,
+            Err(e) => return eprintln!("Failed to fetch edited message: {:?}", e),
+        };
 
-impl EventHandler for Handler {}
+        self.commands.dispatch_edit(message);
+    }
+
+    fn message_delete(&self, _: SerenityContext, channel_id: ChannelId, message_id: MessageId) {
+        self.commands.dispatch_delete(channel_id, message_id);
+    }
+}
 
 fn main() {
     let sleep_dur = Duration::seconds(5).to_std().unwrap();
 
+    let config = match Config::load("config.toml") {
+        Ok(config) => config,
+        Err(e) => {
+            report("Failed to load config.toml", &e);
+            process::exit(1);
+        },
+    };
+
+    // Started once, so a local paste server keeps running across reconnects
+    let paste = match config.paste_service().start(config.playground_timeouts()) {
+        Ok(paste) => paste,
+        Err(e) => {
+            report("Failed to start the paste service", &e);
+            process::exit(1);
+        },
+    };
+
     loop {   
         println!("{} Starting up", Utc::now());
 
-        match run() {
+        match run(&config, &paste) {
             Ok(()) => eprintln!("[OK] Disconnected for an unknown reason"),
-            Err(e) => {
-                eprintln!("[ERR] Disconnected");
-
-                for cause in e.causes() {
-                    eprintln!("[ERR] Caused by: {}", cause);
-                }
-            }
+            Err(e) => report("Disconnected", &e),
         }
 
         eprintln!("Reconnecting in 5 seconds");
@@ -65,19 +107,39 @@ fn main() {
     }
 }
 
-pub fn run() -> Result<(), Error> {
-    //    let mut codedb = ::codedb::CodeDB::open_or_create("code_db.json")?;
+fn report(action: &str, e: &Error) {
+    eprintln!("[ERR] {}", action);
 
-    let config = Config::load("config.toml")?;
-    let mut client = Client::new(config.token(), Handler).map_err(|e| SyncFailure::new(e))?;
-    
-    let mut commands = CommandRegistry::new("?");
+    for cause in e.causes() {
+        eprintln!("[ERR] Caused by: {}", cause);
+    }
+}
 
-    module::CrateInfo::init(&mut commands);
-    module::Help::init(&mut commands);
-    module::Egg::init(&mut commands);
-    module::Playground::init(&mut commands);
+pub fn run(config: &Config, paste: &Arc<Paste>) -> Result<(), Error> {
+    //    let mut codedb = ::codedb::CodeDB::open_or_create("code_db.json")?;
 
+    let mut commands = CommandRegistry::new(
+        "?",
+        config.reply_settings().clone(),
+        paste.clone(),
+        config.max_evaluations(),
+    );
+
+    module::CrateInfo::init(&mut commands, config);
+    module::Help::init(&mut commands, config);
+    module::Egg::init(&mut commands, config);
+    module::Playground::init(&mut commands, config);
+    module::Tools::init(&mut commands, config);
+    module::Bisect::init(&mut commands, config);
+    module::Bench::init(&mut commands, config);
+
+    let commands = SharedRegistry::new(commands);
+    let handler = Handler {
+        commands: commands.clone(),
+    };
+
+    let mut client = Client::new(config.token(), handler).map_err(|e| SyncFailure::new(e))?;
+    
     client.with_framework(commands);
 
     // reactor blocks until a disconnection or other in `irc` error
@@ -0,0 +1,145 @@
+use module::prelude::*;
+use playground::{self, ExecuteRequest, Channel, Mode};
+use reqwest::Client;
+use http;
+use super::playground::{command_code, format_nanos, render_prelude, Outcome};
+use super::tools::{excerpt, reply_full_output};
+
+const MAX_CANDIDATES: usize = 5;
+const MAX_LABEL_LEN: usize = 30;
+const WARMUP_BATCHES: usize = 10;
+const SAMPLES: usize = 30;
+/// Starts the lines that the harness reports the results on.
+const RESULT_MARKER: &str = "playbot-bench: ";
+
+pub enum Bench {}
+
+impl Module for Bench {
+    fn init(commands: &mut CommandRegistry, config: &Config) {
+        let http = http::client(config.playground_timeouts());
+        let prelude = config.prelude().to_vec();
+
+        commands.set_named_handler("bench", move |ctx: &Context, _: &[&str]| {
+            bench_handler(ctx, &http, &prelude)
+        });
+    }
+}
+
+/// The statistics of a candidate, in nanoseconds per iteration.
+struct Measurement {
+    mean: f64,
+    median: f64,
+    stddev: f64,
+}
+
+/// Benchmarks each line of the code as a candidate in release mode.
+/// Candidates are expressions or closures without arguments.
+fn bench_handler(ctx: &Context, http: &Client, prelude: &[String]) -> Flow {
+    let code = command_code(ctx);
+    let candidates = code.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    if candidates.is_empty() {
+        return Flow::Continue;
+    }
+
+    if candidates.len() > MAX_CANDIDATES {
+        ctx.reply(format!("At most {} candidates can be benchmarked at once", MAX_CANDIDATES));
+        return Flow::Break;
+    }
+
+    let _slot = match ctx.evaluation_slot() {
+        Some(slot) => slot,
+        None => return Flow::Break,
+    };
+
+    let harness = harness(&candidates, prelude);
+    let mut request = ExecuteRequest::new(harness.as_str());
+    request.set_channel(Channel::Stable);
+    request.set_mode(Mode::Release);
+
+    let resp = match playground::execute(http, &request) {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("Failed to run benchmark: {:?}", e);
+            ctx.reply(http::error_reply("Failed to run benchmark", &e));
+            return Flow::Break;
+        }
+    };
+
+    let outcome = Outcome::of(&resp);
+    let results = resp.stdout.lines()
+        .filter(|line| line.starts_with(RESULT_MARKER))
+        .filter_map(|line| parse_measurement(&line[RESULT_MARKER.len()..]))
+        .collect::<Vec<_>>();
+
+    if !outcome.is_success() || results.len() != candidates.len() {
+        if outcome.is_success() {
+            ctx.reply("The benchmark did not report results for every candidate");
+        } else {
+            ctx.reply(outcome.to_string());
+        }
+
+        reply_full_output(ctx, &harness, &resp, Channel::Stable);
+        return Flow::Break;
+    }
+
+    let fastest = results.iter()
+        .map(|result| result.mean)
+        .fold(::std::f64::INFINITY, f64::min);
+
+    let lines = candidates.iter().zip(&results).map(|(candidate, result)| {
+        let comparison = if candidates.len() == 1 {
+            String::new()
+        } else if result.mean <= fastest {
+            " (fastest)".to_string()
+        } else {
+            format!(" ({:.2}× slower)", result.mean / fastest)
+        };
+
+        format!("{}: mean {}, median {}, σ {}{}",
+            excerpt(candidate, MAX_LABEL_LEN),
+            format_nanos(result.mean),
+            format_nanos(result.median),
+            format_nanos(result.stddev),
+            comparison,
+        )
+    }).collect::<Vec<_>>();
+
+    ctx.reply(lines.join("\n"));
+
+    Flow::Break
+}
+
+fn harness(candidates: &[&str], prelude: &[String]) -> String {
+    let benches = candidates.iter().enumerate().map(|(i, candidate)| {
+        let closure = if candidate.starts_with("||") || candidate.starts_with("move ||") {
+            candidate.to_string()
+        } else {
+            format!("|| {{ {} }}", candidate)
+        };
+
+        format!("    report({}, bench({}));\n", i, closure)
+    }).collect::<String>();
+
+    format!(include_str!("../../bench_template.rs"),
+        prelude = render_prelude(prelude, &candidates.join("\n")),
+        warmup = WARMUP_BATCHES,
+        samples = SAMPLES,
+        marker = RESULT_MARKER,
+        candidates = benches,
+    )
+}
+
+/// Parses a `<candidate> <mean> <median> <stddev>` line of the harness.
+fn parse_measurement(line: &str) -> Option<Measurement> {
+    let mut fields = line.split_whitespace().skip(1).map(|field| field.parse::<f64>().ok());
+
+    Some(Measurement {
+        mean: fields.next()??,
+        median: fields.next()??,
+        stddev: fields.next()??,
+    })
+}
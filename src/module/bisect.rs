@@ -0,0 +1,126 @@
+use module::prelude::*;
+use playground::{self, ExecuteRequest};
+use playground::local::Sandbox;
+use regex::Regex;
+use super::playground::{command_body, strip_markdown, summary, Template};
+
+lazy_static! {
+    static ref DATED_NIGHTLY: Regex = Regex::new(r"^nightly-(\d{4}-\d{2}-\d{2})").unwrap();
+}
+
+pub enum Bisect {}
+
+impl Module for Bisect {
+    fn init(commands: &mut CommandRegistry, config: &Config) {
+        // Bisecting runs code with the local toolchains
+        let sandbox = match config.local_sandbox() {
+            Some(sandbox) => sandbox.clone(),
+            None => return,
+        };
+        let prelude = config.prelude().to_vec();
+
+        commands.set_named_handler("bisect", move |ctx: &Context, _: &[&str]| {
+            bisect_handler(ctx, &sandbox, &prelude)
+        });
+    }
+}
+
+/// Binary searches the locally installed nightlies for the first one
+/// on which the snippet's result differs from the oldest nightly.
+fn bisect_handler(ctx: &Context, sandbox: &Sandbox, prelude: &[String]) -> Flow {
+    let mut body = command_body(ctx);
+    let flag = body.split_whitespace().next().unwrap_or("");
+    let bare = flag == "--bare" || flag == "--mini";
+
+    // Flags come before the code block, so they are parsed before stripping markdown
+    if bare {
+        body = &body[flag.len()..];
+    }
+
+    let code = strip_markdown(body);
+
+    if code.is_empty() {
+        return Flow::Continue;
+    }
+
+    let _slot = match ctx.evaluation_slot() {
+        Some(slot) => slot,
+        None => return Flow::Break,
+    };
+
+    let code = if bare { code.to_string() } else { Template::Debug.wrap(code, prelude) };
+
+    let mut nightlies = match playground::local::toolchains() {
+        Ok(toolchains) => toolchains.into_iter()
+            .filter_map(|toolchain| DATED_NIGHTLY.captures(&toolchain)
+                .and_then(|caps| caps.get(0))
+                .map(|nightly| nightly.as_str().to_string()))
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            eprintln!("Failed to list toolchains: {:?}", e);
+            ctx.reply("Failed to list installed toolchains");
+            return Flow::Break;
+        }
+    };
+
+    // Dates are ISO 8601, so lexical order is chronological order
+    nightlies.sort();
+    nightlies.dedup();
+
+    if nightlies.len() < 2 {
+        ctx.reply("At least two dated nightlies need to be installed to bisect");
+        return Flow::Break;
+    }
+
+    let request = ExecuteRequest::new(code.as_str());
+    let outcome = |nightly: &str| -> Option<(bool, String)> {
+        match playground::local::execute(&request, nightly, sandbox) {
+            Ok(resp) => Some((resp.success, summary(&resp))),
+            Err(e) => {
+                eprintln!("Failed to execute code with '{}': {:?}", nightly, e);
+                None
+            }
+        }
+    };
+
+    let mut good = 0;
+    let mut bad = nightlies.len() - 1;
+
+    let (first, last) = match (outcome(&nightlies[good]), outcome(&nightlies[bad])) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            ctx.reply("Failed to run the installed nightlies");
+            return Flow::Break;
+        }
+    };
+
+    if first == last {
+        ctx.reply(format!("No change from {} to {}: {}", nightlies[good], nightlies[bad], first.1));
+        return Flow::Break;
+    }
+
+    let mut last_changed = last;
+
+    while bad - good > 1 {
+        let mid = good + (bad - good) / 2;
+
+        let current = match outcome(&nightlies[mid]) {
+            Some(current) => current,
+            None => {
+                ctx.reply(format!("Failed to run {}", nightlies[mid]));
+                return Flow::Break;
+            }
+        };
+
+        if current == first {
+            good = mid;
+        } else {
+            bad = mid;
+            last_changed = current;
+        }
+    }
+
+    ctx.reply(format!("{}: {}\n{}: {}", nightlies[good], first.1, nightlies[bad], last_changed.1));
+
+    Flow::Break
+}
@@ -2,32 +2,38 @@ use module::prelude::*;
 use cratesio;
 use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
 use itertools::Itertools;
-use reqwest::StatusCode::NotFound;
+use reqwest::Client;
+use reqwest::StatusCode;
+use http;
 
 pub enum CrateInfo {}
 
 impl Module for CrateInfo {
-    fn init(commands: &mut CommandRegistry) {
-        commands.set_named_handler("crate", crate_handler);
+    fn init(commands: &mut CommandRegistry, config: &Config) {
+        let http = http::client(config.cratesio_timeouts());
+
+        commands.set_named_handler("crate", move |ctx: &Context, args: &[&str]| {
+            crate_handler(ctx, &http, args)
+        });
     }
 }
 
-fn crate_handler(ctx: &Context, args: &[&str]) -> Flow {
+fn crate_handler(ctx: &Context, http: &Client, args: &[&str]) -> Flow {
     let crate_name = match args.get(0) {
         Some(name) => name,
         None => return Flow::Continue,
     };
 
-    let info = match cratesio::crate_info(crate_name) {
+    let info = match http::retry(|| cratesio::crate_info(http, crate_name)) {
         Ok(info) => info,
         // TODO: Use proper error types
-        Err(ref err) if err.status() == Some(NotFound) => {
+        Err(ref err) if err.status() == Some(StatusCode::NOT_FOUND) => {
             ctx.reply(format!("Crate '{}' does not exist.", crate_name));
             return Flow::Break
         },
         Err(err) => {
             eprintln!("Error getting crate info for '{}': {:?}", crate_name, err);
-            ctx.reply(format!("Failed to get crate info for {}", crate_name));
+            ctx.reply(http::error_reply(&format!("Failed to get crate info for {}", crate_name), &err));
             return Flow::Break
         }
     };
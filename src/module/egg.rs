@@ -42,7 +42,7 @@ lazy_static! {
 pub enum Egg {}
 
 impl Module for Egg {
-    fn init(commands: &mut CommandRegistry) {
+    fn init(commands: &mut CommandRegistry, _config: &Config) {
         commands.add_fallback_handler(egg_handler);
     }
 }
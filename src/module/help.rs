@@ -3,7 +3,7 @@ use module::prelude::*;
 pub enum Help {}
 
 impl Module for Help {
-    fn init(commands: &mut CommandRegistry) {
+    fn init(commands: &mut CommandRegistry, _config: &Config) {
         commands.set_named_handler("help", help_handler);
     }
 }
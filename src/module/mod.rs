@@ -13,16 +13,26 @@ pub use self::egg::Egg;
 pub mod help;
 pub use self::help::Help;
 
+pub mod tools;
+pub use self::tools::Tools;
+
+pub mod bisect;
+pub use self::bisect::Bisect;
+
+pub mod bench;
+pub use self::bench::Bench;
+
 mod prelude {
     pub(in super) use {
         Context,
         Flow,
         CommandRegistry,
+        Config,
     };
     pub use super::Module;
     pub use failure::Error;
 }
 
 pub trait Module {
-    fn init(commands: &mut CommandRegistry) where Self: Sized;
+    fn init(commands: &mut CommandRegistry, config: &Config) where Self: Sized;
 }
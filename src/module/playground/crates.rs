@@ -0,0 +1,167 @@
+use regex::Regex;
+use playground::Crates;
+use std::cmp;
+use super::template::{EXTERN_CRATE, declared_items, imported_names};
+
+const MAX_SUGGESTIONS: usize = 3;
+/// Crates that come with the compiler, and path roots that aren't crates.
+const BUILTIN: &[&str] = &["std", "core", "alloc", "proc_macro", "test", "self", "super", "crate", "Self"];
+
+lazy_static! {
+    static ref USE_ROOT: Regex = Regex::new(r"\buse\s+(?:::)?\s*([[:word:]]+)\s*::").unwrap();
+}
+
+/// Collects the crates that `code` refers to via `extern crate` or `use`
+/// but that aren't available on the playground.
+/// Paths starting with a name that the code declares or imports itself,
+/// or that the `prelude` imports, are ignored.
+pub fn unavailable<'a>(code: &'a str, prelude: &[String], crates: &Crates) -> Vec<&'a str> {
+    let mut local = declared_items(code);
+    local.extend(imported_names(code));
+    local.extend(prelude.iter().flat_map(|path| path.rsplit("::").next()));
+
+    let mut used = Vec::new();
+
+    for caps in EXTERN_CRATE.captures_iter(code) {
+        if let Some(alias) = caps.get(2) {
+            local.push(alias.as_str());
+        }
+
+        if let Some(name) = caps.get(1) {
+            used.push(name.as_str());
+        }
+    }
+
+    used.extend(USE_ROOT.captures_iter(code)
+        .flat_map(|caps| caps.get(1))
+        .map(|name| name.as_str())
+        .filter(|name| !local.contains(name)));
+
+    let mut unavailable = Vec::new();
+
+    for name in used {
+        if !BUILTIN.contains(&name) && crates.find(name).is_none() && !unavailable.contains(&name) {
+            unavailable.push(name);
+        }
+    }
+
+    unavailable
+}
+
+/// Describes that `name` is not available, suggesting crates with similar names.
+pub fn not_available(name: &str, crates: &Crates) -> String {
+    let similar = similar(name, crates)
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>();
+
+    if similar.is_empty() {
+        format!("Crate `{}` is not available on the playground", name)
+    } else {
+        format!("Crate `{}` is not available on the playground (did you mean {}?)", name, similar.join(", "))
+    }
+}
+
+/// Finds the available crates with names close to `name`, closest first.
+fn similar<'a>(name: &str, crates: &'a Crates) -> Vec<&'a str> {
+    let name = name.replace('-', "_").to_lowercase();
+    let max_distance = cmp::max(1, name.chars().count() / 3);
+
+    let mut similar = crates.crates().iter()
+        .map(|krate| (distance(&name, krate.id()), krate.name()))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .collect::<Vec<_>>();
+
+    similar.sort();
+    similar.into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// The Levenshtein distance between two names.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..b.len() + 1).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = diagonal + if a == b { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = cmp::min(substitution, cmp::min(row[j], diagonal) + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use playground::Crates;
+    use super::{distance, unavailable};
+
+    fn crates() -> Crates {
+        serde_json::from_str(r#"{"crates": [
+            {"name": "rand", "version": "0.5.5", "id": "rand"},
+            {"name": "serde-json", "version": "1.0.27", "id": "serde_json"}
+        ]}"#).unwrap()
+    }
+
+    fn prelude() -> Vec<String> {
+        vec!["std::fmt".to_string(), "std::io".to_string()]
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(distance("rand", "rand"), 0);
+        assert_eq!(distance("rnad", "rand"), 2);
+        assert_eq!(distance("rands", "rand"), 1);
+        assert_eq!(distance("", "rand"), 4);
+        assert_eq!(distance("serde_jsno", "serde_json"), 2);
+    }
+
+    #[test]
+    fn finds_unavailable_crates() {
+        let code = "extern crate rand; extern crate nope; use serde_json::Value; use other::Thing;";
+        assert_eq!(unavailable(code, &[], &crates()), vec!["nope", "other"]);
+    }
+
+    #[test]
+    fn reports_each_crate_once() {
+        let code = "extern crate nope; use nope::a; use nope::b;";
+        assert_eq!(unavailable(code, &[], &crates()), vec!["nope"]);
+    }
+
+    #[test]
+    fn ignores_builtin_crates() {
+        let code = "use std::fmt; use core::mem; use self::a::B; use super::C;";
+        assert!(unavailable(code, &[], &crates()).is_empty());
+    }
+
+    #[test]
+    fn ignores_local_names() {
+        let code = "mod a { pub struct B; } enum E { V } use a::B; use E::*;";
+        assert!(unavailable(code, &[], &crates()).is_empty());
+
+        let code = "extern crate rand as r; use r::Rng;";
+        assert!(unavailable(code, &[], &crates()).is_empty());
+    }
+
+    #[test]
+    fn ignores_imported_names() {
+        let code = "use std::collections; use collections::HashMap;";
+        assert!(unavailable(code, &[], &crates()).is_empty());
+    }
+
+    #[test]
+    fn ignores_names_from_the_prelude() {
+        let code = "use fmt::Write; use io::Read;";
+        assert!(unavailable(code, &prelude(), &crates()).is_empty());
+        assert_eq!(unavailable(code, &[], &crates()), vec!["fmt", "io"]);
+    }
+}
@@ -0,0 +1,375 @@
+//! A lightweight scanner that separates item declarations
+//! (`struct`, `impl`, `fn`, `use`, …) from the statements of a snippet.
+//!
+//! It only tracks nesting, strings, chars and comments,
+//! which is enough to find the top level boundaries of items.
+
+/// Splits `code` into the items at its top level and the remaining statements,
+/// each in their original order and formatting.
+pub fn split_items(code: &str) -> (String, String) {
+    let mut items = String::new();
+    let mut rest = String::new();
+    // A semicolon terminated item that contains a braced expression,
+    // e.g. `const P: Point = Point { x: 1 };`
+    let mut unterminated: Option<String> = None;
+
+    for chunk in chunks(code) {
+        let terminated = chunk.trim_right().ends_with(';');
+
+        if let Some(mut item) = unterminated.take() {
+            item.push_str(chunk);
+
+            if terminated {
+                items.push_str(&item);
+            } else {
+                unterminated = Some(item);
+            }
+
+            continue;
+        }
+
+        match item_kind(chunk) {
+            Some(ItemKind::Semicolon) if !terminated => unterminated = Some(chunk.to_string()),
+            Some(_) => items.push_str(chunk),
+            None => rest.push_str(chunk),
+        }
+    }
+
+    // Leave incomplete items where they were and let rustc report them
+    if let Some(item) = unterminated {
+        rest.push_str(&item);
+    }
+
+    (items, rest)
+}
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+enum ItemKind {
+    /// Ends with either a `;` or a closing brace, e.g. `fn` or `struct`
+    Block,
+    /// Always ends with a `;`, e.g. `use` or `const`
+    Semicolon,
+}
+
+/// Splits `code` after each `;` and `}` at the top level.
+fn chunks(code: &str) -> Vec<&str> {
+    let bytes = code.as_bytes();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = skip_line_comment(bytes, i);
+                continue;
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_block_comment(bytes, i);
+                continue;
+            },
+            b'"' => {
+                i = skip_string(bytes, i);
+                continue;
+            },
+            b'r' if is_raw_string_start(bytes, i) => {
+                i = skip_raw_string(bytes, i);
+                continue;
+            },
+            b'\'' => {
+                i = skip_char_or_lifetime(code, i);
+                continue;
+            },
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b'}' => {
+                depth = depth.saturating_sub(1);
+
+                if depth == 0 {
+                    chunks.push(&code[start..i + 1]);
+                    start = i + 1;
+                }
+            },
+            b';' if depth == 0 => {
+                chunks.push(&code[start..i + 1]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+
+        i += 1;
+    }
+
+    if start < code.len() {
+        chunks.push(&code[start..]);
+    }
+
+    chunks
+}
+
+fn item_kind(chunk: &str) -> Option<ItemKind> {
+    let mut rest = skip_attributes_and_comments(chunk);
+
+    if starts_with_word(rest, "pub") {
+        rest = rest[3..].trim_left();
+
+        if rest.starts_with('(') {
+            rest = &rest[rest.find(')')? + 1..];
+        }
+    }
+
+    loop {
+        rest = rest.trim_left();
+        let word = rest.split(|c: char| !is_ident_char(c)).next().unwrap_or("");
+        rest = &rest[word.len()..];
+
+        match word {
+            "unsafe" | "async" | "default" => continue,
+            "const" => {
+                let next = rest.trim_left();
+
+                if starts_with_word(next, "fn") || starts_with_word(next, "unsafe") || starts_with_word(next, "extern") {
+                    continue;
+                }
+
+                return Some(ItemKind::Semicolon);
+            },
+            "extern" => {
+                rest = rest.trim_left();
+
+                // ABI string, e.g. `extern "C" fn`
+                if rest.starts_with('"') {
+                    rest = &rest[skip_string(rest.as_bytes(), 0)..];
+                }
+
+                let next = rest.trim_left();
+
+                if starts_with_word(next, "crate") {
+                    return Some(ItemKind::Semicolon);
+                }
+
+                if next.starts_with('{') {
+                    return Some(ItemKind::Block);
+                }
+            },
+            "static" | "use" | "type" => return Some(ItemKind::Semicolon),
+            // The template declares `main` itself, so the snippet's `main` stays inside of it
+            "fn" if starts_with_word(rest.trim_left(), "main") => return None,
+            // Only a keyword in front of a name, e.g. not in `union.len()`
+            "union" if rest.starts_with(char::is_whitespace) && rest.trim_left().starts_with(is_ident_char) => {
+                return Some(ItemKind::Block);
+            },
+            "struct" | "enum" | "trait" | "impl" | "fn" | "mod" => return Some(ItemKind::Block),
+            "macro_rules" if rest.starts_with('!') => return Some(ItemKind::Block),
+            _ => return None,
+        }
+    }
+}
+
+fn skip_attributes_and_comments(mut code: &str) -> &str {
+    loop {
+        code = code.trim_left();
+        let bytes = code.as_bytes();
+
+        let end = if code.starts_with("//") {
+            skip_line_comment(bytes, 0)
+        } else if code.starts_with("/*") {
+            skip_block_comment(bytes, 0)
+        } else if code.starts_with("#[") {
+            skip_attribute(code)
+        } else {
+            return code;
+        };
+
+        code = &code[end..];
+    }
+}
+
+fn skip_attribute(code: &str) -> usize {
+    let mut depth = 0;
+
+    for (i, b) in code.bytes().enumerate() {
+        match b {
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return i + 1;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    code.len()
+}
+
+fn skip_line_comment(bytes: &[u8], start: usize) -> usize {
+    bytes[start..].iter()
+        .position(|&b| b == b'\n')
+        .map(|pos| start + pos)
+        .unwrap_or(bytes.len())
+}
+
+fn skip_block_comment(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+
+    while i + 1 < bytes.len() {
+        match (bytes[i], bytes[i + 1]) {
+            (b'/', b'*') => {
+                depth += 1;
+                i += 2;
+            },
+            (b'*', b'/') => {
+                depth -= 1;
+                i += 2;
+
+                if depth == 0 {
+                    return i;
+                }
+            },
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+fn is_raw_string_start(bytes: &[u8], start: usize) -> bool {
+    let prefix = if start > 0 && bytes[start - 1] == b'b' { start - 1 } else { start };
+
+    if prefix > 0 && is_ident_char(bytes[prefix - 1] as char) {
+        return false;
+    }
+
+    let hashes = bytes[start + 1..].iter().take_while(|&&b| b == b'#').count();
+
+    bytes.get(start + 1 + hashes) == Some(&b'"')
+}
+
+fn skip_raw_string(bytes: &[u8], start: usize) -> usize {
+    let hashes = bytes[start + 1..].iter().take_while(|&&b| b == b'#').count();
+    let mut i = start + 1 + hashes + 1;
+
+    while i < bytes.len() {
+        if bytes[i] == b'"' && bytes[i + 1..].iter().take(hashes).filter(|&&b| b == b'#').count() == hashes {
+            return i + 1 + hashes;
+        }
+
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+/// Distinguishes char literals (`'a'`, `'\n'`) from lifetimes and labels (`'a`).
+fn skip_char_or_lifetime(code: &str, start: usize) -> usize {
+    let bytes = code.as_bytes();
+
+    if bytes.get(start + 1) == Some(&b'\\') {
+        return bytes[start + 2..].iter()
+            .position(|&b| b == b'\'')
+            .map(|pos| start + 2 + pos + 1)
+            .unwrap_or(bytes.len());
+    }
+
+    let char_len = code[start + 1..].chars().next().map(char::len_utf8).unwrap_or(0);
+
+    if bytes.get(start + 1 + char_len) == Some(&b'\'') {
+        start + 1 + char_len + 1
+    } else {
+        start + 1
+    }
+}
+
+fn starts_with_word(code: &str, word: &str) -> bool {
+    code.starts_with(word) && !code[word.len()..].starts_with(is_ident_char)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_items;
+
+    fn split(code: &str) -> (String, String) {
+        let (items, rest) = split_items(code);
+        (items.trim().to_string(), rest.trim().to_string())
+    }
+
+    fn assert_split(code: &str, items: &str, rest: &str) {
+        assert_eq!(split(code), (items.to_string(), rest.to_string()), "splitting {:?}", code);
+    }
+
+    #[test]
+    fn hoists_items() {
+        assert_split("#[derive(Debug)] struct A; A", "#[derive(Debug)] struct A;", "A");
+        assert_split("use std::mem; fn f() {} mem::size_of::<u8>()", "use std::mem; fn f() {}", "mem::size_of::<u8>()");
+    }
+
+    #[test]
+    fn keeps_items_with_braced_expressions_whole() {
+        assert_split(
+            "struct P { x: i32 } const X: P = P { x: 1 }; X.x",
+            "struct P { x: i32 } const X: P = P { x: 1 };",
+            "X.x",
+        );
+    }
+
+    #[test]
+    fn ignores_delimiters_in_strings() {
+        assert_split(r#"let s = "};"; fn f() {} s"#, "fn f() {}", r#"let s = "};"; s"#);
+        assert_split(r#"let s = "\"}"; s"#, "", r#"let s = "\"}"; s"#);
+    }
+
+    #[test]
+    fn ignores_delimiters_in_raw_strings() {
+        assert_split(r##"let s = r#"}"; {"#; s"##, "", r##"let s = r#"}"; {"#; s"##);
+        assert_split(r##"let s = br"}"; s"##, "", r##"let s = br"}"; s"##);
+    }
+
+    #[test]
+    fn distinguishes_chars_from_lifetimes() {
+        assert_split(
+            "fn f<'a>(x: &'a str) -> char { '}' } f(\"\")",
+            "fn f<'a>(x: &'a str) -> char { '}' }",
+            "f(\"\")",
+        );
+        assert_split("let c = '\\''; fn f() {} c", "fn f() {}", "let c = '\\''; c");
+    }
+
+    #[test]
+    fn ignores_delimiters_in_comments() {
+        assert_split("/* } */ struct A; A // }", "/* } */ struct A;", "A // }");
+    }
+
+    #[test]
+    fn only_treats_union_as_item_in_front_of_a_name() {
+        assert_split("union U { a: u32, b: f32 } 1", "union U { a: u32, b: f32 }", "1");
+        assert_split("let union = vec![1]; union.len()", "", "let union = vec![1]; union.len()");
+    }
+
+    #[test]
+    fn keeps_main_in_place() {
+        assert_split("fn main() { println!(\"hi\"); }", "", "fn main() { println!(\"hi\"); }");
+    }
+}
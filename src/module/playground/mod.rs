@@ -0,0 +1,715 @@
+use module::prelude::*;
+use playground::{self, Cache, ExecuteRequest, ExecuteResponse, Channel, Mode};
+use playground::local::{Sandbox, Stats};
+use url::Url;
+use reqwest::{self, Client};
+use http::{self, Timeouts};
+use std::thread;
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+mod outcome;
+pub use self::outcome::{Outcome, warnings};
+
+mod items;
+pub use self::items::split_items;
+
+mod template;
+pub use self::template::{Template, split_crate_attrs, render_prelude};
+
+mod scratchpad;
+pub use self::scratchpad::Scratchpads;
+
+mod timing;
+pub use self::timing::format_nanos;
+
+mod crates;
+
+const CONTINUATION_TIMEOUT: Duration = Duration::from_secs(120);
+/// How long a continued code block may get before it is given up on.
+const MAX_CONTINUATION_LEN: usize = 16 * 1024;
+
+pub enum Playground {}
+
+/// How warnings of successful runs are reported.
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum Warnings {
+    /// Append the number of warnings to the output
+    Count,
+    /// Display the first warning
+    Show,
+    /// Treat warnings as errors
+    Deny,
+}
+
+impl Module for Playground {
+    fn init(commands: &mut CommandRegistry, config: &Config) {
+        let scratchpads = Arc::new(Scratchpads::new());
+        let cache = Arc::new(Cache::new(config.cache_ttl()));
+
+        commands.add_fallback_handler(playground_handler(
+            config.prelude().to_vec(),
+            scratchpads.clone(),
+            cache.clone(),
+            config.playground_timeouts(),
+            config.local_sandbox().cloned(),
+        ));
+        commands.set_named_handler("scratch", scratch_handler(scratchpads));
+        commands.set_named_handler("playcrates", playcrates_handler(cache, config.playground_timeouts()));
+    }
+}
+
+/// Looks up whether a crate is available on the playground.
+fn playcrates_handler(cache: Arc<Cache>, timeouts: Timeouts) -> impl Fn(&Context, &[&str]) -> Flow {
+    let http = http::client(timeouts);
+
+    move |ctx, args| {
+        let name = match args.get(0) {
+            Some(name) => name,
+            None => return Flow::Continue,
+        };
+
+        let crates = match http::retry(|| cache.crates(&http)) {
+            Ok(crates) => crates,
+            Err(e) => {
+                eprintln!("Failed to get playground crates: {:?}", e);
+                ctx.reply(http::error_reply("Failed to get playground crates", &e));
+                return Flow::Break;
+            }
+        };
+
+        match crates.find(name) {
+            Some(krate) => ctx.reply(format!(
+                "`{}` {} is available on the playground as `{}`",
+                krate.name(), krate.version(), krate.id()
+            )),
+            None => ctx.reply(crates::not_available(name, &crates)),
+        }
+
+        Flow::Break
+    }
+}
+
+fn playground_handler(
+    prelude: Vec<String>,
+    scratchpads: Arc<Scratchpads>,
+    cache: Arc<Cache>,
+    timeouts: Timeouts,
+    local_sandbox: Option<Sandbox>,
+) -> impl Fn(&Context) -> Flow {
+    let http = http::client(timeouts);
+    let unfinished = Mutex::new(HashMap::<_, (String, Instant)>::new());
+
+    move |ctx| {
+        let mut unfinished = unfinished.lock().unwrap();
+        let key = (ctx.target(), ctx.source());
+
+        // Drop the code blocks that were abandoned, not only the author's
+        unfinished.retain(|_, &mut (_, since)| since.elapsed() < CONTINUATION_TIMEOUT);
+
+        // Code blocks can be continued in the following messages until the fence is closed
+        let body = match unfinished.remove(&key) {
+            Some((start, _)) => format!("{}\n{}", start, ctx.body()),
+            None if ctx.is_directly_addressed() => ctx.body().to_string(),
+            None => return Flow::Continue,
+        };
+
+        if body.matches("```").count() % 2 == 1 {
+            if body.len() > MAX_CONTINUATION_LEN {
+                ctx.reply("Code block too long, it was never closed with ```");
+                return Flow::Break;
+            }
+
+            unfinished.insert(key, (body, Instant::now()));
+            return Flow::Break;
+        }
+
+        drop(unfinished);
+
+        evaluate(ctx, &http, &cache, local_sandbox.as_ref(), &prelude, &scratchpads, &body)
+    }
+}
+
+fn evaluate(
+    ctx: &Context,
+    http: &Client,
+    cache: &Arc<Cache>,
+    local_sandbox: Option<&Sandbox>,
+    prelude: &[String],
+    scratchpads: &Scratchpads,
+    mut body: &str,
+) -> Flow {
+    // Unset unless given, so they can take precedence over the settings of a URL
+    let mut channel = None;
+    let mut compare_channels = Vec::new();
+    let mut show_version = false;
+    let mut bare = false;
+    let mut mode = None;
+    let mut expand = false;
+    let mut toolchain = None;
+    let mut warnings = Warnings::Count;
+    let mut template = Template::Debug;
+    let mut use_prelude = true;
+    let mut url = None;
+    let mut session = false;
+    let mut time = false;
+    let mut stdin = None;
+
+    // Parse flags
+    loop {
+        body = body.trim_left();
+        let flag = body.split_whitespace().next().unwrap_or("");
+
+        match flag {
+            "--stable" => channel = Some(Channel::Stable),
+            "--beta" => channel = Some(Channel::Beta),
+            "--nightly" => channel = Some(Channel::Nightly),
+            "--version" | "VERSION" => show_version = true,
+            "--bare" | "--mini" => bare = true,
+            "--debug" => mode = Some(Mode::Debug),
+            "--release" => mode = Some(Mode::Release),
+            "--expand" => expand = true,
+            "--display" => template = Template::Display,
+            "--pretty" => template = Template::Pretty,
+            "--type" => template = Template::Type,
+            "--size" => template = Template::Size,
+            "--no-prelude" => use_prelude = false,
+            "--session" => session = true,
+            "--time" => time = true,
+            "--warnings" => warnings = Warnings::Show,
+            "--deny-warnings" => warnings = Warnings::Deny,
+            "--url" => {
+                let rest = body[flag.len()..].trim_left();
+                let arg = rest.split_whitespace().next().unwrap_or("");
+                url = Some(arg);
+                body = &rest[arg.len()..];
+                continue;
+            },
+            "--stdin" => {
+                match parse_argument(body[flag.len()..].trim_left()) {
+                    Some((arg, rest)) => {
+                        stdin = Some(arg);
+                        body = rest;
+                    },
+                    None => {
+                        ctx.reply("--stdin needs an argument, e.g. --stdin \"first line\\nsecond line\"");
+                        return Flow::Break;
+                    }
+                }
+                continue;
+            },
+            "--all-channels" => {
+                compare_channels = vec![Channel::Stable, Channel::Beta, Channel::Nightly];
+            },
+            flag if flag.starts_with("--toolchain=") => {
+                toolchain = Some(flag["--toolchain=".len()..].to_string());
+            },
+            flag if flag.starts_with("--nightly=") => {
+                toolchain = Some(format!("nightly-{}", &flag["--nightly=".len()..]));
+            },
+            flag if flag.starts_with("--compare=") => {
+                let channels = flag["--compare=".len()..]
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<Vec<Channel>, _>>();
+
+                match channels {
+                    Ok(channels) => compare_channels = channels,
+                    Err(e) => {
+                        ctx.reply(e.to_string());
+                        return Flow::Break;
+                    }
+                }
+            },
+            "help" | "h" | "-h" | "-help" | "--help" | "--h" => {
+                super::help::display_help(ctx);
+                return Flow::Break;
+            }
+            _ => break,
+        }
+
+        body = &body[flag.len()..];
+    }
+
+    // Local toolchains run code on the bot's host, so they have to be enabled explicitly
+    if toolchain.is_some() && local_sandbox.is_none() {
+        ctx.reply("Local toolchains are disabled");
+        return Flow::Break;
+    }
+
+    // A second code block is the input of the program
+    if stdin.is_none() {
+        stdin = second_code_block(body).map(|input| format!("{}\n", input));
+    }
+
+    if show_version {
+        print_version(http, cache, channel.unwrap_or(Channel::Stable), ctx);
+        return Flow::Break;
+    }
+
+    // Only wrapped code can time itself, local runs are timed as a whole
+    if time && (url.is_some() || (bare && toolchain.is_none())) {
+        ctx.reply("--time needs wrapped code, or --toolchain for --bare code");
+        return Flow::Break;
+    }
+
+    // Only running code waits for a slot, help and versions are answered right away
+    let _slot = match ctx.evaluation_slot() {
+        Some(slot) => slot,
+        None => return Flow::Break,
+    };
+
+    let snippet = strip_markdown(body);
+
+    let mut request = match url {
+        Some(url) => {
+            let mut request = match gist_request(http, url) {
+                Ok(request) => request,
+                Err(e) => {
+                    eprintln!("Failed to load code from {}: {:?}", url, e);
+                    ctx.reply(format!("Failed to load code from {}: {}", url, e));
+                    return Flow::Break;
+                }
+            };
+
+            // Flags given explicitly take precedence over the URL's settings
+            if let Some(channel) = channel {
+                request.set_channel(channel);
+            }
+
+            if let Some(mode) = mode {
+                request.set_mode(mode);
+            }
+
+            request
+        },
+        None => {
+            let session_code;
+            let body = if session {
+                let (crate_attrs, code) = split_crate_attrs(snippet);
+                session_code = format!("{}\n{}\n{}", crate_attrs, scratchpads.code(ctx.source()), code);
+                &session_code
+            } else {
+                snippet
+            };
+
+            // The prelude imported by the wrapped code
+            // Expansion doesn't resolve names, and the imports would crowd out the excerpt
+            let prelude: &[String] = if use_prelude && !bare && !expand { prelude } else { &[] };
+
+            // Code on the playground can only use the crates it provides
+            if toolchain.is_none() {
+                warn_unavailable_crates(ctx, http, cache, body, prelude);
+            }
+
+            let code = if bare { body.to_string() } else {
+                if time {
+                    template.wrap_timed(body, prelude)
+                } else {
+                    template.wrap(body, prelude)
+                }
+            };
+
+            let mut request = ExecuteRequest::new(code);
+            request.set_channel(channel.unwrap_or(Channel::Stable));
+            request.set_mode(mode.unwrap_or(Mode::Debug));
+            request
+        },
+    };
+
+    if let Some(stdin) = stdin {
+        request.set_stdin(stdin);
+    }
+
+    if expand {
+        super::tools::expand(ctx, http, request.code());
+        return Flow::Break;
+    }
+
+    if !compare_channels.is_empty() {
+        compare(ctx, http, cache, &request, &compare_channels, time);
+        return Flow::Break;
+    }
+
+    let outcome = match (toolchain, local_sandbox) {
+        (Some(toolchain), Some(sandbox)) => execute_local(ctx, &request, &toolchain, sandbox, warnings, time),
+        _ => execute(ctx, http, cache, &request, warnings, time),
+    };
+
+    // Only keep items around that compile
+    // Bare code is a whole program, its items would clash with the wrapped code's
+    if session && !bare && url.is_none() {
+        match outcome {
+            None | Some(Outcome::CompileError { .. }) => {},
+            Some(_) => {
+                let (_, code) = split_crate_attrs(snippet);
+                let (items, _) = split_items(code);
+
+                if !items.trim().is_empty() && !scratchpads.push(ctx.source(), items.trim().to_string()) {
+                    ctx.reply("Your scratchpad is full, see ?scratch undo and ?scratch clear");
+                }
+            }
+        }
+    }
+
+    Flow::Break
+}
+
+fn scratch_handler(scratchpads: Arc<Scratchpads>) -> impl Fn(&Context, &[&str]) -> Flow {
+    move |ctx, args| {
+        let user = ctx.source();
+
+        match args.get(0).cloned().unwrap_or("show") {
+            "show" => {
+                let entries = scratchpads.entries(user);
+
+                if entries.is_empty() {
+                    ctx.reply("Your scratchpad is empty.");
+                } else {
+                    let summary = entries.iter().enumerate().map(|(i, entry)| {
+                        let first_line = entry.lines().next().unwrap_or("");
+                        let ellipsis = if entry.lines().count() > 1 { " …" } else { "" };
+                        format!("{}: {}{}", i + 1, first_line, ellipsis)
+                    }).collect::<Vec<_>>();
+
+                    ctx.reply(summary.join("\n"));
+                }
+            },
+            "undo" => match scratchpads.undo(user) {
+                Some(entry) => ctx.reply(format!("Removed: {}", entry.lines().next().unwrap_or(""))),
+                None => ctx.reply("Your scratchpad is empty."),
+            },
+            "clear" => {
+                scratchpads.clear(user);
+                ctx.reply("Cleared your scratchpad.");
+            },
+            "paste" => {
+                let code = scratchpads.code(user);
+
+                if code.is_empty() {
+                    ctx.reply("Your scratchpad is empty.");
+                } else {
+                    reply_paste(ctx, &code, Channel::Stable, Mode::Debug);
+                }
+            },
+            _ => ctx.reply("Usage: ?scratch [show|undo|clear|paste]"),
+        }
+
+        Flow::Break
+    }
+}
+
+/// Creates a request for the code of a playground share or gist URL,
+/// using the channel, mode and edition of the URL if it has them.
+fn gist_request(http: &Client, url: &str) -> Result<ExecuteRequest<'static>, Error> {
+    let url = Url::parse(url)?;
+    let query = |key: &str| url.query_pairs()
+        .find(|&(ref name, _)| *name == key)
+        .map(|(_, value)| value.into_owned());
+
+    let gist_id = match url.host_str() {
+        Some("play.rust-lang.org") => query("gist")
+            .ok_or_else(|| format_err!("the URL does not contain a gist"))?,
+        Some("gist.github.com") => url.path_segments()
+            .and_then(|segments| segments.filter(|segment| !segment.is_empty()).last())
+            .map(String::from)
+            .ok_or_else(|| format_err!("the URL does not contain a gist"))?,
+        _ => return Err(format_err!("only playground and gist URLs are supported")),
+    };
+
+    let gist = http::retry(|| playground::gist(http, &gist_id))?;
+    let mut request = ExecuteRequest::new(gist.code);
+
+    if let Some(channel) = query("version") {
+        request.set_channel(channel.parse()?);
+    }
+
+    if let Some(mode) = query("mode") {
+        request.set_mode(mode.parse()?);
+    }
+
+    if let Some(edition) = query("edition") {
+        request.set_edition(edition.parse()?);
+    }
+
+    Ok(request)
+}
+
+fn print_version(http: &Client, cache: &Cache, channel: Channel, ctx: &Context) {
+    let resp = match http::retry(|| cache.version(http, channel)) {
+        Err(e) => return {
+            eprintln!("Failed to get version: {:?}", e);
+            ctx.reply(http::error_reply("Failed to get the version", &e));
+        },
+        Ok(resp) => resp,
+    };
+    
+    let version = format!("{version} ({hash:.9} {date})",
+        version = resp.version,
+        hash = resp.hash,
+        date = resp.date,
+    );
+
+    ctx.reply(version);
+}
+
+/// Runs the request on the playground.
+/// Timed requests bypass the cache, as their output differs between runs.
+pub fn execute(ctx: &Context, http: &Client, cache: &Cache, request: &ExecuteRequest, warnings: Warnings, time: bool) -> Option<Outcome> {
+    let resp = execute_cached(http, cache, request, time);
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(e) => return {
+            eprintln!("Failed to execute code: {:?}", e);
+            ctx.reply(http::error_reply("Failed to execute code", &e));
+            None
+        },
+    };
+
+    Some(reply_response(ctx, request, &resp, warnings, time, None))
+}
+
+/// Runs the request on the playground through the cache, unless it is timed.
+/// Runs that timed out aren't cached, as the playground may just have been busy.
+fn execute_cached(http: &Client, cache: &Cache, request: &ExecuteRequest, time: bool) -> Result<ExecuteResponse, reqwest::Error> {
+    if time {
+        playground::execute(http, request)
+    } else {
+        cache.execute(http, request, |resp| Outcome::of(resp) != Outcome::Timeout)
+    }
+}
+
+/// Warns about crates that the code uses but the playground doesn't provide.
+/// The code still runs, as paths like `use Enum::*` may look like crates.
+fn warn_unavailable_crates(ctx: &Context, http: &Client, cache: &Cache, code: &str, prelude: &[String]) {
+    // The warning is optional, so a broken crate list doesn't hold up every evaluation
+    if cache.crates_failing() {
+        return;
+    }
+
+    let crates = match http::retry(|| cache.crates(http)) {
+        Ok(crates) => crates,
+        Err(e) => {
+            eprintln!("Failed to get playground crates: {:?}", e);
+            return;
+        }
+    };
+
+    let warnings = crates::unavailable(code, prelude, &crates)
+        .into_iter()
+        .map(|name| crates::not_available(name, &crates))
+        .collect::<Vec<_>>();
+
+    if !warnings.is_empty() {
+        ctx.reply(warnings.join("\n"));
+    }
+}
+
+fn execute_local(ctx: &Context, request: &ExecuteRequest, toolchain: &str, sandbox: &Sandbox, warnings: Warnings, time: bool) -> Option<Outcome> {
+    if !playground::local::is_valid_toolchain(toolchain) {
+        ctx.reply(format!("Invalid toolchain '{}'", toolchain));
+        return None;
+    }
+
+    let (resp, stats) = match playground::local::execute_with_stats(&request, toolchain, sandbox) {
+        Ok(run) => run,
+        Err(e) => return {
+            eprintln!("Failed to execute code locally with '{}': {:?}", toolchain, e);
+            ctx.reply(format!("Failed to run toolchain '{}'. Is it installed?", toolchain));
+            None
+        },
+    };
+
+    Some(reply_response(ctx, request, &resp, warnings, time, stats.as_ref()))
+}
+
+fn reply_response(
+    ctx: &Context,
+    request: &ExecuteRequest,
+    resp: &ExecuteResponse,
+    warnings_mode: Warnings,
+    time: bool,
+    stats: Option<&Stats>,
+) -> Outcome {
+    let outcome = Outcome::of(resp);
+    let (runtime, stderr) = timing::take_runtime(&resp.stderr);
+    let warnings = warnings(&stderr);
+    let denied = warnings_mode == Warnings::Deny && !warnings.is_empty();
+
+    if !outcome.is_success() {
+        ctx.reply(outcome.to_string());
+    } else if denied {
+        ctx.reply(format!("denied {}", warnings[0]));
+    } else {
+        let mut lines = resp.stdout.lines().take(2).map(String::from).collect::<Vec<_>>();
+
+        if warnings_mode == Warnings::Count && !warnings.is_empty() {
+            let marker = match warnings.len() {
+                1 => "(1 warning)".to_string(),
+                n => format!("({} warnings)", n),
+            };
+
+            match lines.last_mut() {
+                Some(line) => *line = format!("{} {}", line, marker),
+                None => lines.push(marker),
+            }
+        }
+
+        if !lines.is_empty() {
+            ctx.reply_code("rust", lines.join("\n"));
+        }
+
+        if warnings_mode == Warnings::Show {
+            if let Some(warning) = warnings.first() {
+                ctx.reply(warning);
+            }
+        }
+    }
+
+    if time {
+        if let Some(description) = timing::describe(runtime, stats) {
+            ctx.reply(description);
+        }
+    }
+
+    if !outcome.is_success() || denied || resp.stdout.lines().count() > 2 {
+        let code = format!(include_str!("../../../paste_template.rs"),
+            code = request.code(),
+            stdout = resp.stdout,
+            stderr = stderr,
+        );
+
+        reply_paste(ctx, &code, request.channel(), request.mode());
+    }
+
+    outcome
+}
+
+/// Runs the request on all `channels` in parallel and replies
+/// with one line per channel, marking lines that differ from the first channel.
+fn compare(ctx: &Context, http: &Client, cache: &Arc<Cache>, request: &ExecuteRequest, channels: &[Channel], time: bool) {
+    let runs = channels.iter().map(|&channel| {
+        let http = http.clone();
+        let cache = cache.clone();
+        let mut request = request.clone().into_owned();
+        request.set_channel(channel);
+
+        thread::spawn(move || execute_cached(&http, &cache, &request, time).map(|resp| summary(&resp)))
+    }).collect::<Vec<_>>();
+
+    let summaries = runs.into_iter().map(|run| match run.join() {
+        Ok(Ok(summary)) => summary,
+        Ok(Err(e)) => {
+            eprintln!("Failed to execute code: {:?}", e);
+
+            if e.is_timeout() {
+                "<timed out>".to_string()
+            } else {
+                "<failed to execute code>".to_string()
+            }
+        },
+        Err(_) => "<failed to execute code>".to_string(),
+    }).collect::<Vec<_>>();
+
+    if summaries.iter().all(|summary| *summary == summaries[0]) {
+        let channels = channels.iter().map(Channel::as_str).collect::<Vec<_>>();
+        ctx.reply(format!("{}: {} (identical)", channels.join(", "), summaries[0]));
+        return;
+    }
+
+    let lines = channels.iter().zip(&summaries).map(|(channel, summary)| {
+        let marker = if *summary == summaries[0] { "" } else { " [differs]" };
+        format!("{}: {}{}", channel.as_str(), summary, marker)
+    }).collect::<Vec<_>>();
+
+    ctx.reply(lines.join("\n"));
+}
+
+/// A single line summary of the result of an execution.
+pub fn summary(resp: &ExecuteResponse) -> String {
+    match Outcome::of(resp) {
+        Outcome::Success => resp.stdout.lines().next().unwrap_or("(no output)").to_string(),
+        outcome => outcome.to_string(),
+    }
+}
+
+pub fn reply_paste(ctx: &Context, text: &str, channel: Channel, mode: Mode) {
+    if let Some(url) = ctx.paste_code(text, channel, mode) {
+        ctx.reply(format!("~~~ Full output: {}", url));
+    }
+}
+
+/// Returns the code following the command name of a `?command <code>` message.
+pub fn command_code<'a>(ctx: &Context<'a>) -> &'a str {
+    strip_markdown(command_body(ctx))
+}
+
+/// Returns the text following the command name of a `?command <flags> <code>` message,
+/// with markdown still in place, so flags in front of a code block can be parsed.
+pub fn command_body<'a>(ctx: &Context<'a>) -> &'a str {
+    let body = ctx.body().trim_left();
+    let name_len = body.find(char::is_whitespace).unwrap_or(body.len());
+
+    body[name_len..].trim_left()
+}
+
+/// Extracts the code of the second markdown code block, if there is one.
+fn second_code_block(code: &str) -> Option<&str> {
+    let first_start = code.find("```")? + 3;
+    let first_end = first_start + code[first_start..].find("```")? + 3;
+    let rest = &code[first_end..];
+
+    if rest.contains("```") {
+        Some(strip_markdown(rest))
+    } else {
+        None
+    }
+}
+
+/// Parses a command line argument at the start of `text`, returning it and the rest of the text.
+/// Arguments are either a single word or quoted, with `\"`, `\\`, `\n` and `\t` escapes.
+fn parse_argument(text: &str) -> Option<(String, &str)> {
+    if !text.starts_with('"') {
+        let arg = text.split_whitespace().next()?;
+        return Some((arg.to_string(), &text[arg.len()..]));
+    }
+
+    let mut arg = String::new();
+    let mut chars = text.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((arg, &text[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => arg.push('\n'),
+                't' => arg.push('\t'),
+                c => arg.push(c),
+            },
+            c => arg.push(c),
+        }
+    }
+
+    // Unterminated quote
+    None
+}
+
+/// Extracts the code of the first markdown code block (```rust … ```)
+/// or of inline code (`…`).
+pub fn strip_markdown(code: &str) -> &str {
+    let code = code.trim();
+
+    let block_start = match code.find("```") {
+        Some(start) => start + 3,
+        None => return code.trim_matches('`').trim(),
+    };
+
+    let block = &code[block_start..];
+    let block = &block[..block.find("```").unwrap_or(block.len())];
+
+    // Skip the language tag, e.g. ```rust
+    match block.find('\n') {
+        Some(newline) if block[..newline].chars().all(char::is_alphanumeric) => block[newline..].trim(),
+        _ => block.trim(),
+    }
+}
@@ -0,0 +1,284 @@
+//! Classification of playground output.
+//!
+//! The playground only hands back rendered compiler and program output,
+//! so the diagnostics are recognized by their textual form.
+
+use playground::ExecuteResponse;
+use regex::Regex;
+use std::fmt;
+
+lazy_static! {
+    static ref COMPILE_ERROR: Regex = Regex::new(r"^error(\[E\d{4}\])?: ").unwrap();
+    static ref WARNING: Regex = Regex::new(r"^warning: ").unwrap();
+    static ref WARNING_SUMMARY: Regex = Regex::new(r"^warning: .*(\d+ warnings? emitted|generated \d+ warnings?)").unwrap();
+    static ref SPAN: Regex = Regex::new(r"^\s*--> (\S+)").unwrap();
+    // `thread 'main' panicked at 'msg', src/main.rs:2:5`
+    static ref PANIC_OLD: Regex = Regex::new(r"(?m)^thread '.*?' panicked at '(.*)', (\S+)$").unwrap();
+    // `thread 'main' panicked at src/main.rs:2:5:` followed by the message
+    static ref PANIC: Regex = Regex::new(r"(?m)^thread '.*?' panicked at (\S+?):\n(.*)$").unwrap();
+    // The playground's `timeout --signal=KILL` shows up as `Killed`, other signals by their own name
+    static ref TIMEOUT: Regex = Regex::new(r"(?m)(line \d+:\s+\d+ Killed\s+timeout --signal=KILL|^Killed: .* timed out)").unwrap();
+    static ref SIGNAL: Regex = Regex::new(r"(?m)line \d+:\s+\d+ ([A-Z][a-z]+(?: [a-z]+)*)").unwrap();
+    // `process didn't exit successfully: `target/debug/playground` (exit code: 3)`,
+    // newer cargo says `(exit status: 3)`
+    static ref EXIT_CODE: Regex = Regex::new(r"process didn't exit successfully: .*\(exit (?:code|status): (-?\d+)\)").unwrap();
+    // `process didn't exit successfully: `target/debug/playground` (signal: 11, SIGSEGV: invalid memory reference)`
+    static ref EXIT_SIGNAL: Regex = Regex::new(r"process didn't exit successfully: .*\(signal: (\d+)(?:, (SIG[A-Z0-9]+))?").unwrap();
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Outcome {
+    Success,
+    CompileError {
+        message: String,
+        span: Option<String>,
+    },
+    Panic {
+        message: String,
+        location: String,
+    },
+    Timeout,
+    Exit {
+        code: Option<i32>,
+        signal: Option<String>,
+    },
+}
+
+impl Outcome {
+    pub fn of(resp: &ExecuteResponse) -> Self {
+        if resp.success {
+            return Outcome::Success;
+        }
+
+        if let Some(error) = compile_error(&resp.stderr) {
+            return error;
+        }
+
+        if let Some(caps) = PANIC_OLD.captures(&resp.stderr) {
+            return Outcome::Panic {
+                message: caps[1].to_string(),
+                location: caps[2].to_string(),
+            };
+        }
+
+        if let Some(caps) = PANIC.captures(&resp.stderr) {
+            return Outcome::Panic {
+                message: caps[2].to_string(),
+                location: caps[1].to_string(),
+            };
+        }
+
+        if TIMEOUT.is_match(&resp.stderr) {
+            return Outcome::Timeout;
+        }
+
+        let signal = SIGNAL.captures(&resp.stderr)
+            .map(|caps| caps[1].to_string())
+            .or_else(|| EXIT_SIGNAL.captures(&resp.stderr).map(|caps| match caps.get(2) {
+                Some(name) => name.as_str().to_string(),
+                None => format!("signal {}", &caps[1]),
+            }));
+
+        Outcome::Exit {
+            code: EXIT_CODE.captures(&resp.stderr).and_then(|caps| caps[1].parse().ok()),
+            signal,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        *self == Outcome::Success
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Outcome::Success => write!(f, "success"),
+            Outcome::CompileError { ref message, span: Some(ref span) } => write!(f, "{} --> {}", message, span),
+            Outcome::CompileError { ref message, span: None } => write!(f, "{}", message),
+            Outcome::Panic { ref message, ref location } => write!(f, "panicked at {}: {}", location, message),
+            Outcome::Timeout => write!(f, "timed out"),
+            Outcome::Exit { signal: Some(ref signal), .. } => write!(f, "killed by signal ({})", signal),
+            Outcome::Exit { code: Some(code), .. } => write!(f, "exited with code {}", code),
+            Outcome::Exit { code: None, signal: None } => write!(f, "exited unsuccessfully"),
+        }
+    }
+}
+
+/// Finds the first compiler error and its primary span.
+/// Errors after the program started running are not compile errors.
+fn compile_error(stderr: &str) -> Option<Outcome> {
+    let mut lines = stderr.lines()
+        .take_while(|line| !line.trim_left().starts_with("Running `"))
+        .skip_while(|line| !COMPILE_ERROR.is_match(line));
+
+    let message = lines.next()?;
+    let span = lines
+        .take_while(|line| !COMPILE_ERROR.is_match(line))
+        .filter_map(|line| SPAN.captures(line))
+        .map(|caps| caps[1].to_string())
+        .next();
+
+    Some(Outcome::CompileError {
+        message: message.to_string(),
+        span,
+    })
+}
+
+/// Collects the warnings in compiler output, each with its primary span.
+/// The "N warnings emitted" summaries are not counted.
+pub fn warnings(stderr: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut lines = stderr.lines()
+        .take_while(|line| !line.trim_left().starts_with("Running `"))
+        .peekable();
+
+    while let Some(line) = lines.next() {
+        if !WARNING.is_match(line) || WARNING_SUMMARY.is_match(line) {
+            continue;
+        }
+
+        let span = lines.peek()
+            .and_then(|line| SPAN.captures(line))
+            .map(|caps| caps[1].to_string());
+
+        warnings.push(match span {
+            Some(span) => format!("{} --> {}", line, span),
+            None => line.to_string(),
+        });
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use playground::ExecuteResponse;
+    use super::{Outcome, warnings};
+
+    const COMPILED: &str = "   Compiling playground v0.0.1 (/playground)
+    Finished dev [unoptimized + debuginfo] target(s) in 0.62s
+     Running `target/debug/playground`
+";
+
+    fn outcome(stderr: &str) -> Outcome {
+        Outcome::of(&ExecuteResponse {
+            stderr: stderr.to_string(),
+            stdout: String::new(),
+            success: false,
+        })
+    }
+
+    #[test]
+    fn recognizes_success() {
+        let resp = ExecuteResponse {
+            stderr: COMPILED.to_string(),
+            stdout: "1\n".to_string(),
+            success: true,
+        };
+
+        assert_eq!(Outcome::of(&resp), Outcome::Success);
+    }
+
+    #[test]
+    fn finds_compile_error_and_span() {
+        let stderr = "   Compiling playground v0.0.1 (/playground)
+error[E0308]: mismatched types
+ --> src/main.rs:2:18
+  |
+2 |     let x: u32 = \"a\";
+  |            ---   ^^^ expected `u32`, found `&str`
+  |            |
+  |            expected due to this
+
+For more information about this error, try `rustc --explain E0308`.
+error: could not compile `playground` (bin \"playground\") due to 1 previous error
+";
+
+        assert_eq!(outcome(stderr), Outcome::CompileError {
+            message: "error[E0308]: mismatched types".to_string(),
+            span: Some("src/main.rs:2:18".to_string()),
+        });
+    }
+
+    #[test]
+    fn ignores_errors_printed_by_the_program() {
+        let stderr = format!("{}error: not a compile error\n\
+            error: process didn't exit successfully: `target/debug/playground` (exit status: 1)\n", COMPILED);
+
+        assert_eq!(outcome(&stderr), Outcome::Exit { code: Some(1), signal: None });
+    }
+
+    #[test]
+    fn parses_old_panics() {
+        let stderr = format!("{}thread 'main' panicked at 'explicit panic', src/main.rs:2:5\n\
+            note: Run with `RUST_BACKTRACE=1` for a backtrace.\n", COMPILED);
+
+        assert_eq!(outcome(&stderr), Outcome::Panic {
+            message: "explicit panic".to_string(),
+            location: "src/main.rs:2:5".to_string(),
+        });
+    }
+
+    #[test]
+    fn parses_new_panics() {
+        let stderr = format!("{}thread 'main' panicked at src/main.rs:2:5:\n\
+            explicit panic\n\
+            note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n", COMPILED);
+
+        assert_eq!(outcome(&stderr), Outcome::Panic {
+            message: "explicit panic".to_string(),
+            location: "src/main.rs:2:5".to_string(),
+        });
+    }
+
+    #[test]
+    fn recognizes_timeouts() {
+        let stderr = format!("{}/playground/tools/entrypoint.sh: line 11:     8 Killed                  \
+            timeout --signal=KILL ${{timeout}} \"$@\"\n", COMPILED);
+        assert_eq!(outcome(&stderr), Outcome::Timeout);
+
+        let stderr = format!("{}\nKilled: process timed out after 10s\n", COMPILED);
+        assert_eq!(outcome(&stderr), Outcome::Timeout);
+    }
+
+    #[test]
+    fn recognizes_signals() {
+        let stderr = format!("{}/playground/tools/entrypoint.sh: line 11:     8 Segmentation fault      \
+            timeout --signal=KILL ${{timeout}} \"$@\"\n", COMPILED);
+        assert_eq!(outcome(&stderr), Outcome::Exit { code: None, signal: Some("Segmentation fault".to_string()) });
+
+        let stderr = format!("{}error: process didn't exit successfully: `target/debug/playground` \
+            (signal: 11, SIGSEGV: invalid memory reference)\n", COMPILED);
+        assert_eq!(outcome(&stderr), Outcome::Exit { code: None, signal: Some("SIGSEGV".to_string()) });
+    }
+
+    #[test]
+    fn parses_exit_codes() {
+        let stderr = format!("{}error: process didn't exit successfully: `target/debug/playground` (exit code: 3)\n", COMPILED);
+        assert_eq!(outcome(&stderr), Outcome::Exit { code: Some(3), signal: None });
+
+        let stderr = format!("{}error: process didn't exit successfully: `target/debug/playground` (exit status: 101)\n", COMPILED);
+        assert_eq!(outcome(&stderr), Outcome::Exit { code: Some(101), signal: None });
+    }
+
+    #[test]
+    fn collects_warnings_before_the_program_runs() {
+        let stderr = "   Compiling playground v0.0.1 (/playground)
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+warning: `playground` (bin \"playground\") generated 1 warning
+    Finished dev [unoptimized + debuginfo] target(s) in 0.62s
+     Running `target/debug/playground`
+warning: printed by the program
+";
+
+        assert_eq!(warnings(stderr), vec!["warning: unused variable: `x` --> src/main.rs:2:9".to_string()]);
+    }
+}
@@ -0,0 +1,57 @@
+use serenity::model::id::UserId;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const MAX_ENTRIES: usize = 50;
+const MAX_BYTES: usize = 16 * 1024;
+
+/// Per-user item declarations that successive `--session` evaluations build upon.
+#[derive(Default)]
+pub struct Scratchpads {
+    pads: Mutex<HashMap<UserId, Vec<String>>>,
+}
+
+impl Scratchpads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All entries of the user's scratchpad, oldest first.
+    pub fn entries(&self, user: UserId) -> Vec<String> {
+        self.pads.lock().unwrap()
+            .get(&user)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The user's scratchpad as code.
+    pub fn code(&self, user: UserId) -> String {
+        self.entries(user).join("\n")
+    }
+
+    /// Adds an entry, unless the user's scratchpad would exceed its limits.
+    /// Returns whether the entry was added.
+    pub fn push(&self, user: UserId, items: String) -> bool {
+        let mut pads = self.pads.lock().unwrap();
+        let pad = pads.entry(user).or_insert_with(Vec::new);
+        let size = pad.iter().map(String::len).sum::<usize>() + items.len();
+
+        if pad.len() >= MAX_ENTRIES || size > MAX_BYTES {
+            return false;
+        }
+
+        pad.push(items);
+        true
+    }
+
+    /// Removes and returns the most recent entry.
+    pub fn undo(&self, user: UserId) -> Option<String> {
+        self.pads.lock().unwrap()
+            .get_mut(&user)
+            .and_then(|pad| pad.pop())
+    }
+
+    pub fn clear(&self, user: UserId) {
+        self.pads.lock().unwrap().remove(&user);
+    }
+}
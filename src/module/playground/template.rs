@@ -0,0 +1,135 @@
+use regex::Regex;
+use super::items::split_items;
+use super::timing;
+
+lazy_static! {
+    static ref CRATE_ATTRS: Regex = Regex::new(r"^(\s*#!\[.*?\])*").unwrap();
+    static ref TYPE_ITEM: Regex = Regex::new(r"\b(?:struct|enum|union|trait|type|mod)\s+([[:word:]]+)").unwrap();
+    static ref USE_ITEM: Regex = Regex::new(r"\buse\s+([^;]*);").unwrap();
+    pub static ref EXTERN_CRATE: Regex = Regex::new(r"\bextern\s+crate\s+([[:word:]]+)(?:\s+as\s+([[:word:]]+))?").unwrap();
+    static ref WORD: Regex = Regex::new(r"[[:word:]]+").unwrap();
+}
+
+/// The templates that code can be wrapped in.
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum Template {
+    /// Print the value via `Debug`
+    Debug,
+    /// Print the value via `Display`
+    Display,
+    /// Print the value via pretty-printed `Debug`
+    Pretty,
+    /// Print the value and its type
+    Type,
+    /// Print the size and alignment of the value
+    Size,
+}
+
+impl Template {
+    /// Wraps `code` in the template,
+    /// hoisting leading inner attributes to the crate level,
+    /// hoisting item declarations to the module level
+    /// and importing the `prelude` paths that don't clash with the code.
+    pub fn wrap(self, code: &str, prelude: &[String]) -> String {
+        self.render(code, prelude, false)
+    }
+
+    /// Like `wrap`, but the code also reports how long it took to evaluate.
+    pub fn wrap_timed(self, code: &str, prelude: &[String]) -> String {
+        self.render(code, prelude, true)
+    }
+
+    fn render(self, code: &str, prelude: &[String], timed: bool) -> String {
+        let (crate_attrs, code) = split_crate_attrs(code);
+        let prelude = render_prelude(prelude, code);
+        let (items, code) = split_items(code);
+        let code = if timed { timing::timed(&code) } else { code };
+        let (items, code) = (items.as_str(), code.as_str());
+
+        match self {
+            Template::Debug => print_template(crate_attrs, &prelude, items, code, "{:?}"),
+            Template::Display => print_template(crate_attrs, &prelude, items, code, "{}"),
+            Template::Pretty => print_template(crate_attrs, &prelude, items, code, "{:#?}"),
+            Template::Type => format!(include_str!("../../../type_template.rs"),
+                crate_attrs = crate_attrs,
+                prelude = prelude,
+                items = items,
+                code = code,
+            ),
+            Template::Size => format!(include_str!("../../../size_template.rs"),
+                crate_attrs = crate_attrs,
+                prelude = prelude,
+                items = items,
+                code = code,
+            ),
+        }
+    }
+}
+
+/// Splits leading inner attributes, e.g. `#![feature(nll)]`, from the rest of the code.
+pub fn split_crate_attrs(code: &str) -> (&str, &str) {
+    let crate_attrs = CRATE_ATTRS.find(code)
+        .map(|attr| attr.as_str())
+        .unwrap_or("");
+
+    (crate_attrs, &code[crate_attrs.len()..])
+}
+
+fn print_template(crate_attrs: &str, prelude: &str, items: &str, code: &str, format: &str) -> String {
+    format!(include_str!("../../../template.rs"),
+        crate_attrs = crate_attrs,
+        prelude = prelude,
+        items = items,
+        code = code,
+        format = format,
+    )
+}
+
+/// Imports the `prelude` paths that don't clash with names declared by `code`.
+pub fn render_prelude(prelude: &[String], code: &str) -> String {
+    let declared = declared_names(code);
+
+    prelude.iter()
+        .filter(|path| {
+            let name = path.rsplit("::").next().unwrap_or("");
+            name == "*" || !declared.iter().any(|declared| *declared == name)
+        })
+        .map(|path| format!("#[allow(unused_imports)]\nuse {};\n", path))
+        .collect()
+}
+
+/// Collects the names that `code` declares or imports in the type namespace,
+/// which would clash with an import of the same name.
+fn declared_names(code: &str) -> Vec<&str> {
+    let items = declared_items(code);
+
+    // Conservatively treats every word in a `use` as imported
+    let uses = USE_ITEM.captures_iter(code)
+        .flat_map(|caps| caps.get(1))
+        .flat_map(|path| WORD.find_iter(path.as_str()))
+        .map(|name| name.as_str());
+
+    let crates = EXTERN_CRATE.captures_iter(code)
+        .flat_map(|caps| caps.get(2).or(caps.get(1)))
+        .map(|name| name.as_str());
+
+    items.into_iter().chain(uses).chain(crates).collect()
+}
+
+/// Collects the names that the `use` declarations of `code` import,
+/// conservatively treating every word but the root of a path as imported.
+pub fn imported_names(code: &str) -> Vec<&str> {
+    USE_ITEM.captures_iter(code)
+        .flat_map(|caps| caps.get(1))
+        .flat_map(|path| WORD.find_iter(path.as_str()).skip(1))
+        .map(|name| name.as_str())
+        .collect()
+}
+
+/// Collects the names of the types, traits and modules that `code` declares.
+pub fn declared_items(code: &str) -> Vec<&str> {
+    TYPE_ITEM.captures_iter(code)
+        .flat_map(|caps| caps.get(1))
+        .map(|name| name.as_str())
+        .collect()
+}
@@ -0,0 +1,87 @@
+use playground::local::Stats;
+use std::time::Duration;
+
+/// Starts the line that timed code prints its runtime in nanoseconds on.
+const RUNTIME_MARKER: &str = "playbot-runtime-ns: ";
+
+/// Wraps a block of code so it prints its runtime to stderr.
+pub fn timed(code: &str) -> String {
+    format!(include_str!("../../../timed_template.rs"),
+        marker = RUNTIME_MARKER,
+        code = code,
+    )
+}
+
+/// Removes the line printed by timed code from `stderr`,
+/// returning the runtime it reported.
+pub fn take_runtime(stderr: &str) -> (Option<Duration>, String) {
+    let mut runtime = None;
+    let mut rest = String::new();
+
+    for line in stderr.lines() {
+        if line.starts_with(RUNTIME_MARKER) {
+            runtime = line[RUNTIME_MARKER.len()..].trim().parse::<u64>().ok()
+                .map(|nanos| Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32));
+        } else {
+            rest.push_str(line);
+            rest.push('\n');
+        }
+    }
+
+    (runtime, rest)
+}
+
+/// Describes the runtime and, if the code ran locally, the resource usage.
+/// The runtime of the timed code is preferred over the one of the whole process.
+pub fn describe(runtime: Option<Duration>, stats: Option<&Stats>) -> Option<String> {
+    let mut parts = Vec::new();
+
+    match (runtime, stats) {
+        (Some(runtime), _) => parts.push(format!("time: {}", format_duration(runtime))),
+        (None, Some(stats)) => parts.push(format!("time: {} (whole process)", format_duration(stats.runtime))),
+        (None, None) => {},
+    }
+
+    if let Some(stats) = stats {
+        if let Some(peak_memory) = stats.peak_memory {
+            parts.push(format!("peak memory: {}", format_bytes(peak_memory)));
+        }
+
+        parts.push(format!("binary size: {}", format_bytes(stats.binary_size)));
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format_nanos(duration.as_secs() as f64 * 1e9 + f64::from(duration.subsec_nanos()))
+}
+
+/// Formats a number of nanoseconds with a fitting unit.
+pub fn format_nanos(nanos: f64) -> String {
+    if nanos >= 1e9 {
+        format!("{:.3}s", nanos / 1e9)
+    } else if nanos >= 1e6 {
+        format!("{:.3}ms", nanos / 1e6)
+    } else if nanos >= 1e3 {
+        format!("{:.3}µs", nanos / 1e3)
+    } else {
+        format!("{:.0}ns", nanos)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    let bytes = bytes as f64;
+
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} MiB", bytes / (1024.0 * 1024.0))
+    } else if bytes >= 1024.0 {
+        format!("{:.1} KiB", bytes / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
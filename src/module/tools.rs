@@ -0,0 +1,328 @@
+use module::prelude::*;
+use playground::{self, ClippyRequest, FormatRequest, MiriRequest, MacroExpansionRequest, ExecuteResponse, Channel, Mode};
+use reqwest::Client;
+use regex::Regex;
+use http;
+use super::playground::{command_code, reply_paste};
+
+const MAX_FORMATTED_LINES: usize = 3;
+const MAX_EXPANSION_EXCERPT_LINES: usize = 8;
+const MAX_EXPANSION_EXCERPT_LEN: usize = 500;
+
+lazy_static! {
+    static ref DIAGNOSTIC: Regex = Regex::new(r"^(error|warning)(\[\w+\])?: ").unwrap();
+    static ref DIAGNOSTIC_SUMMARY: Regex = Regex::new(
+        r"^(error|warning): (aborting due to|[Cc]ould not compile|build failed|.*\d+ warnings? emitted|.*generated \d+ warnings?)"
+    ).unwrap();
+    static ref CARGO_STATUS: Regex = Regex::new(r"^\s+(Checking|Compiling|Finished|Running|Blocking|Updating|Downloading|Downloaded) ").unwrap();
+}
+
+pub enum Tools {}
+
+impl Module for Tools {
+    fn init(commands: &mut CommandRegistry, config: &Config) {
+        let http = http::client(config.playground_timeouts());
+
+        commands.set_named_handler("clippy", {
+            let http = http.clone();
+            move |ctx: &Context, _: &[&str]| clippy_handler(ctx, &http)
+        });
+
+        commands.set_named_handler("fmt", {
+            let http = http.clone();
+            move |ctx: &Context, _: &[&str]| fmt_handler(ctx, &http)
+        });
+
+        commands.set_named_handler("miri", {
+            let http = http.clone();
+            move |ctx: &Context, _: &[&str]| miri_handler(ctx, &http)
+        });
+
+        commands.set_named_handler("expand", {
+            let http = http.clone();
+            move |ctx: &Context, _: &[&str]| expand_handler(ctx, &http)
+        });
+    }
+}
+
+fn clippy_handler(ctx: &Context, http: &Client) -> Flow {
+    let code = command_code(ctx);
+
+    if code.is_empty() {
+        return Flow::Continue;
+    }
+
+    let _slot = match ctx.evaluation_slot() {
+        Some(slot) => slot,
+        None => return Flow::Break,
+    };
+
+    let resp = match playground::clippy(http, &ClippyRequest::new(code)) {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("Failed to run clippy: {:?}", e);
+            ctx.reply(http::error_reply("Failed to run clippy", &e));
+            return Flow::Break;
+        }
+    };
+
+    match first_diagnostic(&resp.stderr) {
+        Some(diagnostic) => {
+            ctx.reply(diagnostic);
+
+            // A single short lint is summed up well enough by its first line
+            let diagnostics = diagnostic_lines(&resp.stderr);
+            let count = diagnostics.iter().filter(|line| DIAGNOSTIC.is_match(line)).count();
+
+            if count > 1 || diagnostics.len() > ctx.reply_budget().max_lines {
+                reply_full_output(ctx, code, &resp, Channel::Stable);
+            }
+        },
+        None if resp.success => ctx.reply("Clippy found no issues."),
+        None => {
+            ctx.reply("Clippy failed without a diagnostic.");
+            reply_full_output(ctx, code, &resp, Channel::Stable);
+        },
+    }
+
+    Flow::Break
+}
+
+fn fmt_handler(ctx: &Context, http: &Client) -> Flow {
+    let code = command_code(ctx);
+
+    if code.is_empty() {
+        return Flow::Continue;
+    }
+
+    let _slot = match ctx.evaluation_slot() {
+        Some(slot) => slot,
+        None => return Flow::Break,
+    };
+
+    let resp = match playground::format(http, &FormatRequest::new(code)) {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("Failed to run rustfmt: {:?}", e);
+            ctx.reply(http::error_reply("Failed to run rustfmt", &e));
+            return Flow::Break;
+        }
+    };
+
+    if !resp.success {
+        let diagnostic = first_diagnostic(&resp.stderr)
+            .unwrap_or_else(|| "rustfmt failed without a diagnostic.".into());
+        ctx.reply(diagnostic);
+        return Flow::Break;
+    }
+
+    if resp.code.lines().count() > MAX_FORMATTED_LINES {
+        reply_paste(ctx, &resp.code, Channel::Stable, Mode::Debug);
+    } else {
+        ctx.reply_code("rust", &resp.code);
+    }
+
+    Flow::Break
+}
+
+fn miri_handler(ctx: &Context, http: &Client) -> Flow {
+    let code = command_code(ctx);
+
+    if code.is_empty() {
+        return Flow::Continue;
+    }
+
+    let _slot = match ctx.evaluation_slot() {
+        Some(slot) => slot,
+        None => return Flow::Break,
+    };
+
+    let resp = match playground::miri(http, &MiriRequest::new(code)) {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("Failed to run miri: {:?}", e);
+            ctx.reply(http::error_reply("Failed to run miri", &e));
+            return Flow::Break;
+        }
+    };
+
+    let summary = if resp.success {
+        resp.stdout.lines().next().map(Into::into)
+    } else {
+        first_diagnostic(&resp.stderr)
+    };
+
+    ctx.reply(summary.unwrap_or_else(|| "(no output)".into()));
+
+    if !resp.success || resp.stdout.lines().count() > 1 {
+        reply_full_output(ctx, code, &resp, Channel::Nightly);
+    }
+
+    Flow::Break
+}
+
+fn expand_handler(ctx: &Context, http: &Client) -> Flow {
+    let code = command_code(ctx);
+
+    if code.is_empty() {
+        return Flow::Continue;
+    }
+
+    let _slot = match ctx.evaluation_slot() {
+        Some(slot) => slot,
+        None => return Flow::Break,
+    };
+
+    expand(ctx, http, code);
+
+    Flow::Break
+}
+
+/// Expands the macros in `code` and replies with an excerpt of the first lines
+/// of the expansion, followed by a link to the full expansion.
+pub fn expand(ctx: &Context, http: &Client, code: &str) {
+    let resp = match playground::expand_macros(http, &MacroExpansionRequest::new(code)) {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("Failed to expand macros: {:?}", e);
+            ctx.reply(http::error_reply("Failed to expand macros", &e));
+            return;
+        }
+    };
+
+    if !resp.success {
+        let diagnostic = first_diagnostic(&resp.stderr)
+            .unwrap_or_else(|| "Macro expansion failed without a diagnostic.".into());
+        ctx.reply(diagnostic);
+        reply_full_output(ctx, code, &resp, Channel::Nightly);
+        return;
+    }
+
+    let expansion = without_injected_prelude(&resp.stdout);
+    let excerpt = excerpt_lines(&expansion, MAX_EXPANSION_EXCERPT_LINES, MAX_EXPANSION_EXCERPT_LEN);
+
+    ctx.reply_code("rust", excerpt);
+    reply_paste(ctx, &resp.stdout, Channel::Nightly, Mode::Debug);
+}
+
+/// The lines of an expansion without the std prelude that rustc injects into every crate.
+fn without_injected_prelude(expansion: &str) -> Vec<&str> {
+    let mut lines = expansion.lines().peekable();
+    let mut kept = Vec::new();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        // Only the `#[macro_use]` of the injected `extern crate std`, not the user's own
+        if trimmed == "#[macro_use]" {
+            if lines.peek().map_or(false, |next| next.trim().starts_with("extern crate std;")) {
+                lines.next();
+                continue;
+            }
+        } else if is_injected_prelude(trimmed) {
+            continue;
+        }
+
+        kept.push(line);
+    }
+
+    while kept.first().map_or(false, |line| line.trim().is_empty()) {
+        kept.remove(0);
+    }
+
+    kept
+}
+
+/// Whether `line` is one of the single line parts of the injected std prelude.
+fn is_injected_prelude(line: &str) -> bool {
+    line.starts_with("#![feature(prelude_import)]")
+    || line.starts_with("#![no_std]")
+    || line.starts_with("#[prelude_import]")
+    || line.starts_with("use std::prelude::")
+    || line.starts_with("use ::std::prelude::")
+    || (line.starts_with("#[macro_use]") && line["#[macro_use]".len()..].trim().starts_with("extern crate std;"))
+}
+
+/// Takes the leading `lines` that fit into `max_lines` lines and `max_len` bytes,
+/// marking that the text goes on.
+fn excerpt_lines(lines: &[&str], max_lines: usize, max_len: usize) -> String {
+    let mut shown = Vec::new();
+    let mut len = 0;
+
+    for line in lines {
+        if shown.len() == max_lines || len + line.len() > max_len {
+            break;
+        }
+
+        len += line.len() + 1;
+        shown.push(*line);
+    }
+
+    // A single long line is cut instead
+    if shown.is_empty() {
+        return lines.first().map_or(String::new(), |line| excerpt(line, max_len));
+    }
+
+    let mut excerpt = shown.join("\n");
+
+    if shown.len() < lines.len() {
+        excerpt.push_str("\n…");
+    }
+
+    excerpt
+}
+
+/// Truncates `text` to at most `max_len` bytes on a char boundary.
+pub fn excerpt(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("{}…", &text[..end])
+}
+
+/// Finds the first `error`/`warning` in compiler output,
+/// together with the location it points at.
+fn first_diagnostic(stderr: &str) -> Option<String> {
+    let mut lines = stderr.lines()
+        .skip_while(|line| !line.starts_with("error") && !line.starts_with("warning"));
+
+    let message = lines.next()?;
+    let location = lines.next()
+        .map(str::trim)
+        .filter(|line| line.starts_with("-->"));
+
+    Some(match location {
+        Some(location) => format!("{} {}", message, location),
+        None => message.to_string(),
+    })
+}
+
+/// The lines of compiler output that belong to diagnostics,
+/// without cargo's status lines and the summaries at the end.
+fn diagnostic_lines(stderr: &str) -> Vec<&str> {
+    let mut lines = stderr.lines()
+        .filter(|line| !CARGO_STATUS.is_match(line) && !DIAGNOSTIC_SUMMARY.is_match(line))
+        .collect::<Vec<_>>();
+
+    while lines.last().map_or(false, |line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    lines
+}
+
+pub fn reply_full_output(ctx: &Context, code: &str, resp: &ExecuteResponse, channel: Channel) {
+    let code = format!(include_str!("../../paste_template.rs"),
+        code = code,
+        stdout = resp.stdout,
+        stderr = resp.stderr,
+    );
+
+    reply_paste(ctx, &code, channel, Mode::Debug);
+}
@@ -0,0 +1,24 @@
+use playground::{self, Channel, Mode};
+use reqwest::Client;
+use failure::Error;
+use http::{self, Timeouts};
+use super::Paste;
+
+/// Pastes to public gists through the playground.
+pub struct Gist {
+    http: Client,
+}
+
+impl Gist {
+    pub fn new(timeouts: Timeouts) -> Self {
+        Self {
+            http: http::client(timeouts),
+        }
+    }
+}
+
+impl Paste for Gist {
+    fn paste(&self, text: &str, channel: Channel, mode: Mode) -> Result<String, Error> {
+        Ok(playground::paste(&self.http, text, channel, mode)?)
+    }
+}
@@ -0,0 +1,216 @@
+use playground::{Channel, Mode};
+use failure::{Error, err_msg};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use super::Paste;
+
+const MAX_CONNECTIONS: usize = 32;
+const MAX_LINE_LEN: usize = 8 * 1024;
+const MAX_HEADERS_LEN: usize = 32 * 1024;
+/// How long a client may take to send its request or to receive the paste
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pastes to files in a directory, served by a minimal built-in HTTP server.
+/// Pastes are deleted after they expire.
+pub struct Local {
+    url: String,
+    directory: PathBuf,
+    expiry: Duration,
+}
+
+impl Local {
+    /// Starts serving the pastes in `directory` on `address`.
+    /// At most `MAX_CONNECTIONS` requests are served at once, further connections are closed.
+    pub fn start(address: &str, url: &str, directory: &Path, expiry: Duration) -> Result<Self, Error> {
+        fs::create_dir_all(directory)?;
+
+        let listener = TcpListener::bind(address)?;
+        let served_directory = directory.to_owned();
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        eprintln!("Failed to accept paste request: {:?}", e);
+                        continue;
+                    },
+                };
+
+                if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+
+                let directory = served_directory.clone();
+                let connections = connections.clone();
+
+                thread::spawn(move || {
+                    if let Err(e) = serve(&stream, &directory, expiry) {
+                        eprintln!("Failed to serve paste: {:?}", e);
+                    }
+
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        Ok(Self {
+            url: url.trim_right_matches('/').to_string(),
+            directory: directory.to_owned(),
+            expiry,
+        })
+    }
+
+    fn remove_expired(&self) -> Result<(), Error> {
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let is_paste = path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, is_valid_id);
+
+            if is_paste && is_expired(&path, self.expiry) {
+                fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Paste for Local {
+    fn paste(&self, text: &str, _: Channel, _: Mode) -> Result<String, Error> {
+        if let Err(e) = self.remove_expired() {
+            eprintln!("Failed to remove expired pastes: {:?}", e);
+        }
+
+        let id = paste_id(text);
+        fs::write(self.directory.join(&id), text)?;
+
+        Ok(format!("{}/{}", self.url, id))
+    }
+}
+
+/// Answers a `GET /<id>` request with the paste, everything else with a 404.
+fn serve(stream: &TcpStream, directory: &Path, expiry: Duration) -> Result<(), Error> {
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut reader = BufReader::new(Deadline {
+        stream,
+        deadline: Instant::now() + REQUEST_TIMEOUT,
+    });
+    let mut request_line = String::new();
+    read_line(&mut reader, &mut request_line, MAX_LINE_LEN)?;
+
+    // Skip the headers
+    let mut header = String::new();
+    let mut headers_len = 0;
+    loop {
+        header.clear();
+        let len = read_line(&mut reader, &mut header, MAX_LINE_LEN)?;
+        headers_len += len;
+
+        if headers_len > MAX_HEADERS_LEN {
+            return Err(err_msg("request headers too long"));
+        }
+
+        if len == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let paste = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => read_paste(directory, path.trim_left_matches('/'), expiry),
+        _ => None,
+    };
+    let (status, body) = match paste {
+        Some(ref text) => ("200 OK", text.as_str()),
+        None => ("404 Not Found", "Paste not found or expired"),
+    };
+
+    let mut writer = stream;
+    write!(writer,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+
+    Ok(())
+}
+
+/// Reads a line of at most `max_len` bytes into `line`, failing on longer lines.
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String, max_len: usize) -> Result<usize, Error> {
+    let len = reader.take(max_len as u64).read_line(line)?;
+
+    if len == max_len && !line.ends_with('\n') {
+        return Err(err_msg("request line too long"));
+    }
+
+    Ok(len)
+}
+
+/// Fails reads after the deadline, so slow clients can't hold on to a connection.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl<'a> Read for Deadline<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let now = Instant::now();
+
+        if now >= self.deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "request took too long"));
+        }
+
+        self.stream.set_read_timeout(Some(self.deadline - now))?;
+
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+fn read_paste(directory: &Path, id: &str, expiry: Duration) -> Option<String> {
+    if !is_valid_id(id) {
+        return None;
+    }
+
+    let path = directory.join(id);
+
+    if is_expired(&path, expiry) {
+        return None;
+    }
+
+    fs::read_to_string(path).ok()
+}
+
+fn paste_id(text: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    SystemTime::now().hash(&mut hasher);
+
+    format!("{:016x}", hasher.finish())
+}
+
+/// Ids are checked before touching the file system, so requests can't escape the directory.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_expired(path: &Path, expiry: Duration) -> bool {
+    match fs::metadata(path).and_then(|meta| meta.modified()) {
+        Ok(modified) => modified.elapsed().map(|age| age > expiry).unwrap_or(false),
+        Err(_) => true,
+    }
+}
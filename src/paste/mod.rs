@@ -0,0 +1,64 @@
+use playground::{Channel, Mode};
+use failure::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use http::Timeouts;
+
+mod gist;
+mod local;
+mod post;
+
+pub use self::gist::Gist;
+pub use self::local::Local;
+pub use self::post::Post;
+
+/// A service that long outputs are uploaded to.
+pub trait Paste: Send + Sync {
+    /// Pastes `text`, returning the URL of the paste.
+    /// `channel` and `mode` are preselected if the paste can be run.
+    fn paste(&self, text: &str, channel: Channel, mode: Mode) -> Result<String, Error>;
+}
+
+/// The configured paste service.
+#[derive(Clone, Debug)]
+pub enum PasteService {
+    /// Public gists created through the playground
+    Gist,
+    /// The built-in paste server
+    Local {
+        /// Address the server listens on
+        address: String,
+        /// URL under which the server is reachable from the outside
+        url: String,
+        directory: PathBuf,
+        expiry: Duration,
+    },
+    /// A paste service accepting the text as a POST request,
+    /// answering with the URL of the paste
+    Post {
+        url: String,
+        /// Form field to send the text in, the raw request body is used if unset
+        field: Option<String>,
+    },
+}
+
+impl Default for PasteService {
+    fn default() -> Self {
+        PasteService::Gist
+    }
+}
+
+impl PasteService {
+    /// Creates the paste service, starting the server of a local one.
+    /// Requests to remote services fail after the `timeouts`.
+    /// The service outlives reconnects, so this is called only once.
+    pub fn start(&self, timeouts: Timeouts) -> Result<Arc<Paste>, Error> {
+        Ok(match *self {
+            PasteService::Gist => Arc::new(Gist::new(timeouts)),
+            PasteService::Local { ref address, ref url, ref directory, expiry } =>
+                Arc::new(Local::start(address, url, directory, expiry)?),
+            PasteService::Post { ref url, ref field } => Arc::new(Post::new(url, field.clone(), timeouts)),
+        })
+    }
+}
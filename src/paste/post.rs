@@ -0,0 +1,44 @@
+use playground::{Channel, Mode};
+use reqwest::Client;
+use failure::{Error, err_msg};
+use http::{self, Timeouts};
+use super::Paste;
+
+/// Pastes by POSTing the text to a URL, which answers with the URL of the paste.
+pub struct Post {
+    http: Client,
+    url: String,
+    field: Option<String>,
+}
+
+impl Post {
+    pub fn new(url: impl Into<String>, field: Option<String>, timeouts: Timeouts) -> Self {
+        Self {
+            http: http::client(timeouts),
+            url: url.into(),
+            field,
+        }
+    }
+}
+
+impl Paste for Post {
+    fn paste(&self, text: &str, _: Channel, _: Mode) -> Result<String, Error> {
+        let request = self.http.post(&self.url);
+        let request = match self.field {
+            Some(ref field) => request.form(&[(field.as_str(), text)]),
+            None => request.body(text.to_string()),
+        };
+
+        let url = request
+            .send()?
+            .error_for_status()?
+            .text()?;
+        let url = url.trim();
+
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(err_msg(format!("paste service answered without a URL: {:?}", url)));
+        }
+
+        Ok(url.to_string())
+    }
+}
@@ -0,0 +1,44 @@
+use serenity::model::id::MessageId;
+use std::collections::{HashMap, VecDeque};
+
+/// Remembers which bot messages answered which user message,
+/// so the answers can be updated when the user message changes.
+pub struct ReplyLog {
+    capacity: usize,
+    replies: HashMap<MessageId, Vec<MessageId>>,
+    order: VecDeque<MessageId>,
+}
+
+impl ReplyLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            replies: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Records the replies to `source`, forgetting the oldest entry when full.
+    pub fn insert(&mut self, source: MessageId, replies: Vec<MessageId>) {
+        if self.replies.insert(source, replies).is_none() {
+            self.order.push_back(source);
+        }
+
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.replies.remove(&oldest);
+            }
+        }
+    }
+
+    /// Removes and returns the replies to `source`.
+    pub fn take(&mut self, source: MessageId) -> Vec<MessageId> {
+        match self.replies.remove(&source) {
+            Some(replies) => {
+                self.order.retain(|&id| id != source);
+                replies
+            },
+            None => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use serenity::model::id::MessageId;
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+
+/// Serializes the handling of each message in the order its events arrived,
+/// so an edit or deletion only takes over the replies once the previous version is answered.
+#[derive(Default)]
+pub struct Turns {
+    queues: Mutex<HashMap<MessageId, Queue>>,
+    changed: Condvar,
+}
+
+/// The turns taken for a message.
+#[derive(Default)]
+struct Queue {
+    issued: u64,
+    serving: u64,
+}
+
+impl Turns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the next turn for handling `message`.
+    pub fn take(&self, message: MessageId) -> u64 {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues.entry(message).or_insert_with(Queue::default);
+        let turn = queue.issued;
+
+        queue.issued += 1;
+        turn
+    }
+
+    /// Waits until `turn` is up, which lasts until the returned guard is dropped.
+    pub fn wait(&self, message: MessageId, turn: u64) -> Turn {
+        let mut queues = self.queues.lock().unwrap();
+
+        while queues.get(&message).map(|queue| queue.serving) != Some(turn) {
+            queues = self.changed.wait(queues).unwrap();
+        }
+
+        Turn { turns: self, message }
+    }
+
+    fn finish(&self, message: MessageId) {
+        let mut queues = self.queues.lock().unwrap();
+        let done = match queues.get_mut(&message) {
+            Some(queue) => {
+                queue.serving += 1;
+                queue.serving == queue.issued
+            },
+            None => false,
+        };
+
+        if done {
+            queues.remove(&message);
+        }
+
+        self.changed.notify_all();
+    }
+}
+
+/// The turn of a message's handler, passing on to the next when dropped.
+pub struct Turn<'a> {
+    turns: &'a Turns,
+    message: MessageId,
+}
+
+impl<'a> Drop for Turn<'a> {
+    fn drop(&mut self) {
+        self.turns.finish(self.message);
+    }
+}
@@ -1,7 +1,8 @@
 {crate_attrs}
-
+{prelude}
+{items}
 fn main() {{
-    println!("{{:?}}", {{
+    println!("{format}", {{
         {code}
     }});
 }}
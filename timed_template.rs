@@ -0,0 +1,9 @@
+{{
+    let __playbot_start = ::std::time::Instant::now();
+    let __playbot_value = {{
+        {code}
+    }};
+    let __playbot_elapsed = __playbot_start.elapsed();
+    eprintln!("{marker}{{}}", __playbot_elapsed.as_secs() * 1_000_000_000 + __playbot_elapsed.subsec_nanos() as u64);
+    __playbot_value
+}}
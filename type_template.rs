@@ -0,0 +1,14 @@
+{crate_attrs}
+{prelude}
+{items}
+fn type_name_of<T: ?Sized>(_: &T) -> &'static str {{
+    ::std::any::type_name::<T>()
+}}
+
+fn main() {{
+    match {{
+        {code}
+    }} {{
+        value => println!("{{:?}}: {{}}", value, type_name_of(&value)),
+    }}
+}}